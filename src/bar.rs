@@ -9,15 +9,25 @@ use serde_yaml as serde_fmt;
 #[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
 use toml as serde_fmt;
 
-use tokio::prelude::stream::{self, Stream};
+use futures::sync::oneshot;
+use inotify::{Inotify, WatchMask};
+use tokio::prelude::{Future, Stream};
+use tokio::runtime::Runtime;
+use tokio::timer::Delay;
 
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
 use std::io::{Error as IOError, ErrorKind, Read};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::components::{Component, ComponentID, ComponentStream};
+use crate::components::{Component, ComponentID, ComponentRegistry};
 use crate::config::{Background, Component as ConfigComponent, Config};
-use crate::event::Event;
+use crate::error::Error;
+use crate::event::{ComponentPosition, Event, Point};
 
 pub use crate::config::{Border, Monitor, Position};
 
@@ -27,7 +37,7 @@ pub use crate::config::{Border, Monitor, Position};
 /// `Bar` can be created by loading it from a configuration file using the [`load`] method.
 ///
 /// Using the `Bar` struct, it is possible to query for updates using the [`recv`] and [`try_recv`]
-/// methods. These will return the ID of the component which has been updated.
+/// methods. These will return an [`Update`] which describes what has changed.
 ///
 /// To access any component, the [`left`], [`center`], [`right`], and [`components`] methods can be
 /// used.
@@ -35,7 +45,12 @@ pub use crate::config::{Border, Monitor, Position};
 /// It is required to make use of the [`notify`] method to let components know about updates to the
 /// frontend of the bar.
 ///
+/// The configuration can be changed at runtime using the [`reload`] and [`watch`] methods.
+///
 /// [`load`]: #method.load
+/// [`reload`]: #method.reload
+/// [`watch`]: #method.watch
+/// [`Update`]: enum.Update.html
 /// [`left`]: #method.left
 /// [`center`]: #method.center
 /// [`right`]: #method.right
@@ -48,19 +63,106 @@ pub struct Bar {
     left: Vec<Component>,
     center: Vec<Component>,
     right: Vec<Component>,
-    events: Option<(Sender<ComponentID>, Receiver<ComponentID>)>,
+    sources: HashMap<ComponentID, ConfigComponent>,
+    registry: ComponentRegistry,
+    events: (Sender<Message>, Receiver<Message>),
+    updates: VecDeque<Update>,
+    runtime: Option<Runtime>,
+    tasks: HashMap<ComponentID, oneshot::Sender<()>>,
+    positions: HashMap<ComponentID, ComponentPosition>,
+    hovered: Option<ComponentID>,
+    pointer: Option<Point>,
+    // Incremented whenever a pending tooltip is invalidated by the pointer
+    hover_serial: u64,
+    tooltip: Option<ComponentID>,
+    reload_error: Option<Error>,
+}
+
+/// Update of the bar state.
+///
+/// Updates are returned by the [`recv`] and [`try_recv`] methods and indicate which part of the
+/// bar needs to be redrawn.
+///
+/// [`recv`]: struct.Bar.html#method.recv
+/// [`try_recv`]: struct.Bar.html#method.try_recv
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Update {
+    /// The state of a single component has changed.
+    Component(ComponentID),
+
+    /// The [`General`] settings have been changed by a configuration reload.
+    ///
+    /// [`General`]: struct.General.html
+    General,
+
+    /// Components have been added, removed or reordered by a configuration reload.
+    Layout,
+
+    /// The tooltip of a component should be shown at the position of the pointer.
+    ///
+    /// This is queued once the pointer has rested on a component with a tooltip for the
+    /// [`General::tooltip_delay`]. The text of the tooltip can be retrieved with the
+    /// [`Component::tooltip`] method.
+    ///
+    /// [`General::tooltip_delay`]: struct.General.html#structfield.tooltip_delay
+    /// [`Component::tooltip`]: ../components/struct.Component.html#method.tooltip
+    ShowTooltip(ComponentID, Point),
+
+    /// The tooltip which is currently shown should be hidden.
+    ///
+    /// This is queued when the pointer leaves the component or clicks on it.
+    HideTooltip,
+
+    /// The watched configuration file could not be reloaded.
+    ///
+    /// The previous configuration is kept. The reason can be retrieved with the
+    /// [`Bar::reload_error`] method.
+    ///
+    /// [`Bar::reload_error`]: struct.Bar.html#method.reload_error
+    ReloadFailed,
+}
+
+// Messages sent to the bar from the event loop and the config watcher
+enum Message {
+    Component(ComponentID),
+    Action(ComponentID, Result<(), String>),
+    Reload(PathBuf),
+    Tooltip(u64),
+}
+
+/// Alignment of a component inside the bar.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Display for Alignment {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Alignment::Left => write!(f, "left"),
+            Alignment::Center => write!(f, "center"),
+            Alignment::Right => write!(f, "right"),
+        }
+    }
 }
 
 /// General bar settings.
 ///
-/// The general settings are used to setup the bar. These will never change during the runtime of
-/// the bar and are only required for the initial setup.
+/// The general settings are used to setup the bar. These are only changed when the configuration
+/// is reloaded, which is signaled by an [`Update::General`].
+///
+/// [`Update::General`]: enum.Update.html#variant.General
+#[derive(PartialEq)]
 pub struct General {
     pub height: u8,
     pub position: Position,
     pub background: Background,
     pub border: Option<Border>,
     pub monitors: Vec<Monitor>,
+    /// Milliseconds the pointer has to rest on a component before its tooltip is shown.
+    pub tooltip_delay: u64,
 }
 
 impl Bar {
@@ -73,8 +175,8 @@ impl Bar {
     ///
     /// # Errors
     ///
-    /// If the `config_file` cannot be read or its content is not valid. If the configuration is
-    /// invalid, the [`io::ErrorKind::InvalidData`] value is returned.
+    /// If the `config_file` cannot be read or its content is not valid. The returned [`Error`]
+    /// describes which part of the configuration is invalid.
     ///
     /// # Examples
     ///
@@ -95,46 +197,306 @@ impl Bar {
     /// assert_eq!(bar.general().monitors[0].name, "DVI-1");
     /// ```
     ///
-    /// [`io::ErrorKind::InvalidData`]:
-    /// https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData
+    /// [`Error`]: ../enum.Error.html
     /// [`recv`]: #method.recv
     /// [`try_recv`]: #method.try_recv
-    pub fn load<T: Read>(mut config_file: T) -> Result<Self, IOError> {
-        let mut content = String::new();
-        config_file.read_to_string(&mut content)?;
+    pub fn load<T: Read>(config_file: T) -> Result<Self, Error> {
+        Self::load_with_registry(config_file, ComponentRegistry::default())
+    }
+
+    /// Load the initial bar configuration with custom components.
+    ///
+    /// This works just like the [`load`] method, however all components are created using the
+    /// factories in the specified `registry`. This makes it possible to add custom components to
+    /// the bar, or to replace the built-in ones.
+    ///
+    /// # Errors
+    ///
+    /// If the `config_file` cannot be read or its content is not valid. The returned [`Error`]
+    /// describes which part of the configuration is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::Bar;
+    /// use bar_config::components::ComponentRegistry;
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }"
+    /// ));
+    ///
+    /// let bar = Bar::load_with_registry(config_file, ComponentRegistry::default()).unwrap();
+    ///
+    /// assert_eq!(bar.general().height, 30);
+    /// ```
+    ///
+    /// [`load`]: #method.load
+    /// [`Error`]: ../enum.Error.html
+    pub fn load_with_registry<T: Read>(
+        config_file: T,
+        registry: ComponentRegistry,
+    ) -> Result<Self, Error> {
+        let config = parse_config(config_file)?;
 
-        let config: Config =
-            serde_fmt::from_str(&content).map_err(|e| IOError::new(ErrorKind::InvalidData, e))?;
+        let mut bar = Self {
+            general: General::load(&config)?,
+            left: Vec::new(),
+            center: Vec::new(),
+            right: Vec::new(),
+            sources: HashMap::new(),
+            registry,
+            events: mpsc::channel(),
+            updates: VecDeque::new(),
+            runtime: None,
+            tasks: HashMap::new(),
+            positions: HashMap::new(),
+            hovered: None,
+            pointer: None,
+            hover_serial: 0,
+            tooltip: None,
+            reload_error: None,
+        };
 
-        let general = General {
-            height: config.height,
-            position: config.position,
-            background: config.background,
-            border: config.border,
-            monitors: config.monitors,
+        bar.load_components(config)?;
+
+        Ok(bar)
+    }
+
+    /// Reload the bar configuration.
+    ///
+    /// Replaces the current configuration with the content of `config_file`. All components which
+    /// have not been changed are kept alive, so their [`ComponentID`] stays the same. Changed
+    /// components are replaced by new ones.
+    ///
+    /// If the general settings have been changed, an [`Update::General`] will be queued. If any
+    /// component has been added, removed or moved, an [`Update::Layout`] will be queued.
+    ///
+    /// # Errors
+    ///
+    /// If the `config_file` cannot be read or its content is not valid. In this case the current
+    /// configuration is not modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::{Bar, Update};
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }"
+    /// ));
+    /// let mut bar = Bar::load(config_file).unwrap();
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 50\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }"
+    /// ));
+    /// bar.reload(config_file).unwrap();
+    ///
+    /// assert_eq!(bar.general().height, 50);
+    /// assert_eq!(bar.try_recv(), Some(Update::General));
+    /// ```
+    ///
+    /// [`ComponentID`]: ../components/struct.ComponentID.html
+    /// [`Update::General`]: enum.Update.html#variant.General
+    /// [`Update::Layout`]: enum.Update.html#variant.Layout
+    pub fn reload<T: Read>(&mut self, config_file: T) -> Result<(), Error> {
+        let config = parse_config(config_file)?;
+        let general = General::load(&config)?;
+
+        let layout = self.layout();
+        self.load_components(config)?;
+
+        if general != self.general {
+            // Components created by this reload already know about the new monitors
+            if general.monitors != self.general.monitors {
+                for comp in self
+                    .left
+                    .iter_mut()
+                    .chain(&mut self.center)
+                    .chain(&mut self.right)
+                {
+                    if comp.set_monitors(&general.monitors) {
+                        self.updates.push_back(Update::Component(comp.id()));
+                    }
+                }
+            }
+
+            self.general = general;
+            self.updates.push_back(Update::General);
+        }
+
+        if layout != self.layout() {
+            self.updates.push_back(Update::Layout);
+        }
+
+        if self.runtime.is_some() {
+            self.spawn_tasks();
+        }
+
+        Ok(())
+    }
+
+    /// Reload the configuration whenever the file changes.
+    ///
+    /// This watches the file at `path` for changes and automatically calls [`reload`] with its new
+    /// content. The resulting updates are returned by the [`recv`] and [`try_recv`] methods.
+    ///
+    /// If the new content of the file is not a valid configuration, it is ignored and the current
+    /// configuration is kept. An [`Update::ReloadFailed`] is returned in that case and the error
+    /// can be retrieved with the [`reload_error`] method.
+    ///
+    /// # Errors
+    ///
+    /// If the `path` does not point to a file or it cannot be watched.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bar_config::bar::Bar;
+    /// use std::fs::File;
+    ///
+    /// let path = "/home/user/.config/mybar.yml";
+    ///
+    /// let mut bar = Bar::load(File::open(path).unwrap()).unwrap();
+    /// bar.watch(path).unwrap();
+    /// ```
+    ///
+    /// [`reload`]: #method.reload
+    /// [`recv`]: #method.recv
+    /// [`try_recv`]: #method.try_recv
+    /// [`reload_error`]: #method.reload_error
+    /// [`Update::ReloadFailed`]: enum.Update.html#variant.ReloadFailed
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref().canonicalize()?;
+        let (dir, name) = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => (dir.to_path_buf(), name.to_owned()),
+            _ => {
+                let err = IOError::new(ErrorKind::InvalidInput, "path is not a file");
+                return Err(Error::Io(err));
+            }
         };
 
-        // Convert component struct to trait and set general fallbacks
-        let defaults = config.defaults.clone();
-        let convert = |mut comps: Vec<ConfigComponent>| {
+        // Watch the directory to also catch editors replacing the file
+        let mut inotify = Inotify::init()?;
+        inotify.add_watch(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+
+        let events_tx = self.events.0.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            while let Ok(mut events) = inotify.read_events_blocking(&mut buffer) {
+                let modified = events.any(|e| e.name == Some(name.as_os_str()));
+                if modified && events_tx.send(Message::Reload(path.clone())).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    // Replace all components, reusing running components which have not been changed
+    fn load_components(&mut self, config: Config) -> Result<(), Error> {
+        let mut unused: Vec<ComponentID> = self.components().iter().map(|c| c.id()).collect();
+
+        // Create all new components first, so the bar stays intact if one of them is invalid
+        let containers = vec![
+            (Alignment::Left, config.left),
+            (Alignment::Center, config.center),
+            (Alignment::Right, config.right),
+        ];
+        let mut layout = Vec::new();
+        for (alignment, sources) in containers {
+            let mut comps = Vec::new();
+            for (index, mut source) in sources.into_iter().enumerate() {
+                source.settings.fallback(&config.defaults);
+
+                match unused.iter().position(|id| self.sources[id] == source) {
+                    Some(position) => comps.push((unused.remove(position), None, source)),
+                    None => {
+                        let monitors = &config.monitors;
+                        let comp = self.create_component(alignment, index, &source, monitors)?;
+                        comps.push((comp.id(), Some(comp), source));
+                    }
+                }
+            }
+            layout.push(comps);
+        }
+
+        let mut running: HashMap<ComponentID, Component> = {
+            let comps = self.left.drain(..).chain(self.center.drain(..));
             comps
-                .drain(..)
-                .map(|mut c| {
-                    c.settings.fallback(&defaults);
-                    c.into()
-                }).collect()
+                .chain(self.right.drain(..))
+                .map(|c| (c.id(), c))
+                .collect()
         };
-        let left = convert(config.left);
-        let center = convert(config.center);
-        let right = convert(config.right);
-
-        Ok(Self {
-            general,
-            left,
-            center,
-            right,
-            events: None,
-        })
+
+        // Stop the streams of all components which have been removed
+        for comp_id in &unused {
+            self.tasks.remove(comp_id);
+            self.positions.remove(comp_id);
+            if self.hovered == Some(*comp_id) {
+                self.hovered = None;
+                self.hover_serial += 1;
+                self.hide_tooltip();
+            }
+        }
+
+        self.sources.clear();
+        let mut containers = Vec::new();
+        for comps in layout {
+            let mut container = Vec::new();
+            for (comp_id, comp, source) in comps {
+                self.sources.insert(comp_id, source);
+                container.push(comp.unwrap_or_else(|| running.remove(&comp_id).unwrap()));
+            }
+            containers.push(container);
+        }
+
+        self.right = containers.pop().unwrap();
+        self.center = containers.pop().unwrap();
+        self.left = containers.pop().unwrap();
+
+        Ok(())
+    }
+
+    // Create a new component from its configuration
+    fn create_component(
+        &self,
+        alignment: Alignment,
+        index: usize,
+        source: &ConfigComponent,
+        monitors: &[Monitor],
+    ) -> Result<Component, Error> {
+        let settings = source.settings.clone().load()?;
+        let states = source.states.clone().load()?;
+        let mut comp = self
+            .registry
+            .create(&source.name, settings, source.extra.clone())
+            .map_err(|error| Error::Component {
+                alignment,
+                index,
+                error,
+            })?;
+
+        comp.set_monitors(monitors);
+        comp.set_tooltip(source.tooltip.clone());
+        comp.set_actions(source.actions.clone());
+        comp.set_states(states);
+
+        Ok(comp)
+    }
+
+    // IDs of all components in their current order
+    fn layout(&self) -> [Vec<ComponentID>; 3] {
+        let ids = |comps: &Vec<Component>| comps.iter().map(|c| c.id()).collect();
+        [ids(&self.left), ids(&self.center), ids(&self.right)]
     }
 
     /// Blocking poll for updates.
@@ -157,21 +519,21 @@ impl Bar {
     /// ));
     ///
     /// let mut bar = Bar::load(config_file).unwrap();
-    /// let component_id = bar.recv();
+    /// let update = bar.recv();
     ///
-    /// println!("Component {:?} was updated!", component_id);
+    /// println!("Received update {:?}!", update);
     /// ```
-    pub fn recv(&mut self) -> ComponentID {
-        if self.events.is_none() {
-            self.events = Some(self.start_loop());
-        }
+    pub fn recv(&mut self) -> Update {
+        self.start_loop();
 
-        // Process updates until the first dirty component is found
+        // Process messages until the first update is found
         loop {
-            let comp_id = self.events.as_ref().unwrap().1.recv().unwrap();
-            if self.update_component(comp_id) {
-                return comp_id;
+            if let Some(update) = self.updates.pop_front() {
+                return update;
             }
+
+            let message = self.events.1.recv().unwrap();
+            self.process_message(message);
         }
     }
 
@@ -197,31 +559,59 @@ impl Bar {
     /// let mut bar = Bar::load(config_file).unwrap();
     /// let update = bar.try_recv();
     ///
-    /// if let Some(component_id) = update {
-    ///     println!("Component {:?} was updated!", component_id);
+    /// if let Some(update) = update {
+    ///     println!("Received update {:?}!", update);
     /// } else {
     ///     println!("No new event!");
     /// }
     /// ```
-    pub fn try_recv(&mut self) -> Option<ComponentID> {
-        if self.events.is_none() {
-            self.events = Some(self.start_loop());
-        }
+    pub fn try_recv(&mut self) -> Option<Update> {
+        self.start_loop();
 
-        // Process updates until the first dirty component is found
+        // Process messages until the first update is found
         loop {
-            match self.events.as_ref().unwrap().1.try_recv() {
-                Ok(comp_id) => {
-                    if self.update_component(comp_id) {
-                        return Some(comp_id);
-                    }
-                }
+            if let Some(update) = self.updates.pop_front() {
+                return Some(update);
+            }
+
+            match self.events.1.try_recv() {
+                Ok(message) => self.process_message(message),
                 Err(TryRecvError::Empty) => return None,
                 Err(e) => return Err(e).unwrap(),
             }
         }
     }
 
+    // Queue all updates caused by a message
+    fn process_message(&mut self, message: Message) {
+        match message {
+            Message::Component(comp_id) => {
+                if self.update_component(comp_id) {
+                    self.updates.push_back(Update::Component(comp_id));
+                }
+            }
+            Message::Action(comp_id, result) => {
+                if self.finish_action(comp_id, result) {
+                    self.updates.push_back(Update::Component(comp_id));
+                }
+            }
+            Message::Reload(path) => {
+                // Invalid configurations are reported, but the bar keeps running with the old one
+                let result = File::open(path)
+                    .map_err(Error::from)
+                    .and_then(|config_file| self.reload(config_file));
+                match result {
+                    Ok(()) => self.reload_error = None,
+                    Err(err) => {
+                        self.reload_error = Some(err);
+                        self.updates.push_back(Update::ReloadFailed);
+                    }
+                }
+            }
+            Message::Tooltip(serial) => self.show_tooltip(serial),
+        }
+    }
+
     // Update the component with the matching ID
     fn update_component(&mut self, comp_id: ComponentID) -> bool {
         for comp in self.components_mut() {
@@ -232,10 +622,20 @@ impl Bar {
         false
     }
 
+    // Store the result of an action for the component with the matching ID
+    fn finish_action(&mut self, comp_id: ComponentID, result: Result<(), String>) -> bool {
+        for comp in self.components_mut() {
+            if comp.id() == comp_id {
+                return comp.finish_action(result);
+            }
+        }
+        false
+    }
+
     /// General bar settings.
     ///
     /// These settings store all settings that are not directly associated to any component. This
-    /// should only be required during startup, since it is never modified at runtime.
+    /// should only be required during startup and after receiving an [`Update::General`].
     ///
     /// # Examples
     ///
@@ -254,10 +654,22 @@ impl Bar {
     ///
     /// assert_eq!(general.height, 30);
     /// ```
+    ///
+    /// [`Update::General`]: enum.Update.html#variant.General
     pub fn general(&self) -> &General {
         &self.general
     }
 
+    /// Error of the last reload of the watched configuration file.
+    ///
+    /// This is set after receiving an [`Update::ReloadFailed`] and cleared once the file has been
+    /// reloaded successfully.
+    ///
+    /// [`Update::ReloadFailed`]: enum.Update.html#variant.ReloadFailed
+    pub fn reload_error(&self) -> Option<&Error> {
+        self.reload_error.as_ref()
+    }
+
     /// Left bar components.
     ///
     /// Vector with all components which should be rendered at the left side of the bar. These
@@ -387,14 +799,29 @@ impl Bar {
             .collect()
     }
 
-    /// Send an event to all components.
+    /// Send an event to the components.
+    ///
+    /// Notifies the affected components that a new event is available. The components then have
+    /// the choice to react upon the event or ignore it completely.
     ///
-    /// Notifies all components that a new event is available. The components then have the choice
-    /// to react upon the event or ignore it completely.
+    /// The positions of all components are tracked using the [`PositionChange`] events, so
+    /// [`Click`] and [`MouseMotion`] events are only sent to the component below the pointer.
+    /// Whenever the pointer moves to a different component, the [`MouseLeave`] and [`MouseEnter`]
+    /// events are sent to the previous and the new component.
+    ///
+    /// Once the pointer rests on a component with a tooltip for the [`General::tooltip_delay`],
+    /// an [`Update::ShowTooltip`] is queued. The delay only starts once the event loop has been
+    /// started by [`recv`] or [`try_recv`]. Moving to another component or clicking queues an
+    /// [`Update::HideTooltip`].
     ///
     /// If a component handles the event and marks itself as `dirty` as a result of the event, a
     /// new redraw request will be queued for the [`recv`] and [`try_recv`] methods.
     ///
+    /// Clicks run the commands configured with the `on_click_*` and `on_scroll_*` options in the
+    /// background, starting the event loop if necessary. Failing commands are reported through
+    /// [`Component::error`] and if `refresh_on_action` is set, the component is polled again once
+    /// its command has exited.
+    ///
     /// # Examples
     ///
     /// ```
@@ -414,44 +841,266 @@ impl Bar {
     ///
     /// [`recv`]: #method.recv
     /// [`try_recv`]: #method.try_recv
+    /// [`PositionChange`]: ../event/enum.Event.html#variant.PositionChange
+    /// [`Click`]: ../event/enum.Event.html#variant.Click
+    /// [`MouseMotion`]: ../event/enum.Event.html#variant.MouseMotion
+    /// [`MouseEnter`]: ../event/enum.Event.html#variant.MouseEnter
+    /// [`MouseLeave`]: ../event/enum.Event.html#variant.MouseLeave
+    /// [`General::tooltip_delay`]: struct.General.html#structfield.tooltip_delay
+    /// [`Update::ShowTooltip`]: enum.Update.html#variant.ShowTooltip
+    /// [`Update::HideTooltip`]: enum.Update.html#variant.HideTooltip
+    /// [`Component::error`]: ../components/struct.Component.html#method.error
     pub fn notify(&mut self, event: Event) {
-        // Find all dirty components
+        // Find all dirty components and the commands which should be run
         let mut dirty_comps = Vec::new();
-        for comp in self.components_mut() {
-            if comp.notify(event) {
-                dirty_comps.push(comp.id());
+        let mut actions = Vec::new();
+        for (comp_id, event) in self.route(event) {
+            let position = self.positions.get(&comp_id).copied();
+            for comp in self.components_mut() {
+                if comp.id() != comp_id {
+                    continue;
+                }
+
+                let dirty = match position {
+                    Some(position) => comp.notify_at(event, position),
+                    None => comp.notify(event),
+                };
+
+                // The component is redrawn even if the update itself didn't change anything
+                if dirty {
+                    comp.update();
+                    dirty_comps.push(comp_id);
+                }
+                actions.extend(comp.action(event).map(|action| (comp_id, action)));
             }
         }
 
-        if let Some((ref events_tx, _)) = self.events {
-            for comp_id in dirty_comps {
-                events_tx.send(comp_id).unwrap();
+        for comp_id in dirty_comps {
+            self.updates.push_back(Update::Component(comp_id));
+        }
+
+        if actions.is_empty() {
+            return;
+        }
+
+        self.start_loop();
+        let runtime = match self.runtime {
+            Some(ref mut runtime) => runtime,
+            None => return,
+        };
+
+        // Report the result of the commands back to the bar's thread
+        for (comp_id, action) in actions {
+            let events_tx = self.events.0.clone();
+            let task = action.then(move |result| {
+                events_tx
+                    .send(Message::Action(comp_id, result))
+                    .map_err(|_| ())
+            });
+            runtime.spawn(task);
+        }
+    }
+
+    /// Find the component at a point on the screen.
+    ///
+    /// This uses the positions of the components which have been sent to the bar with the
+    /// [`PositionChange`] event. If there is no component at the `point`, `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::Bar;
+    /// use bar_config::event::{ComponentPosition, Event, Point};
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }\n\
+    ///      left:\n\
+    ///       - { text: \"test\" }"
+    /// ));
+    ///
+    /// let mut bar = Bar::load(config_file).unwrap();
+    /// let comp_id = bar.left()[0].id();
+    /// bar.notify(Event::PositionChange(ComponentPosition {
+    ///     comp_id,
+    ///     min_x: 0,
+    ///     max_x: 100,
+    ///     min_y: 0,
+    ///     max_y: 30,
+    /// }));
+    ///
+    /// assert_eq!(bar.component_at(Point { x: 50, y: 10 }), Some(comp_id));
+    /// assert_eq!(bar.component_at(Point { x: 150, y: 10 }), None);
+    /// ```
+    ///
+    /// [`PositionChange`]: ../event/enum.Event.html#variant.PositionChange
+    pub fn component_at(&self, point: Point) -> Option<ComponentID> {
+        self.components()
+            .iter()
+            .map(|comp| comp.id())
+            .find(|comp_id| {
+                self.positions
+                    .get(comp_id)
+                    .map_or(false, |pos| pos.contains(point))
+            })
+    }
+
+    // Select the components which should receive an event
+    fn route(&mut self, event: Event) -> Vec<(ComponentID, Event)> {
+        match event {
+            Event::PositionChange(position) => {
+                self.positions.insert(position.comp_id, position);
+                vec![(position.comp_id, event)]
+            }
+            Event::Click(_, _, point) => {
+                // Clicking cancels the tooltip until the pointer is moved again
+                self.hover_serial += 1;
+                self.hide_tooltip();
+
+                self.component_at(point)
+                    .map(|id| (id, event))
+                    .into_iter()
+                    .collect()
+            }
+            Event::MouseMotion(point) => {
+                let hovered = self.component_at(point);
+                let mut events = Vec::new();
+                if hovered != self.hovered {
+                    events.extend(self.hovered.map(|id| (id, Event::MouseLeave(point))));
+                    events.extend(hovered.map(|id| (id, Event::MouseEnter(point))));
+                    self.hovered = hovered;
+                    self.hide_tooltip();
+                }
+                events.extend(hovered.map(|id| (id, event)));
+
+                self.pointer = Some(point);
+                if self.tooltip.is_none() {
+                    self.schedule_tooltip();
+                }
+
+                events
             }
+            // Pointer crossings are only synthesized by the bar itself
+            Event::MouseEnter(_) | Event::MouseLeave(_) => Vec::new(),
         }
     }
 
-    // Starts the event loop in a new thread
-    fn start_loop(&self) -> (Sender<ComponentID>, Receiver<ComponentID>) {
-        let (events_tx, events_rx) = mpsc::channel();
-        let bar_events_tx = events_tx.clone();
+    // Show the tooltip of the hovered component once the pointer has rested on it
+    //
+    // Pointer motion never starts the event loop, the tooltip is scheduled once it is started.
+    fn schedule_tooltip(&mut self) {
+        self.hover_serial += 1;
+        if self.hovered.is_none() {
+            return;
+        }
+
+        let runtime = match self.runtime {
+            Some(ref mut runtime) => runtime,
+            None => return,
+        };
+
+        let serial = self.hover_serial;
+        let events_tx = self.events.0.clone();
+        let delay = Duration::from_millis(self.general.tooltip_delay);
+        let task = Delay::new(Instant::now() + delay)
+            .then(move |_| events_tx.send(Message::Tooltip(serial)).map_err(|_| ()));
+        runtime.spawn(task);
+    }
 
-        // Combine all component events into one blocking event stream
-        let mut combined: ComponentStream = Box::new(stream::empty());
-        for comp in self.components() {
-            combined = Box::new(combined.select(comp.stream()));
+    // Queue the tooltip if the pointer has not moved since it has been scheduled
+    fn show_tooltip(&mut self, serial: u64) {
+        if serial != self.hover_serial || self.tooltip.is_some() {
+            return;
         }
 
-        thread::spawn(move || {
+        let (comp_id, point) = match (self.hovered, self.pointer) {
+            (Some(comp_id), Some(point)) => (comp_id, point),
+            _ => return,
+        };
+
+        let has_tooltip = self
+            .components()
+            .iter()
+            .any(|comp| comp.id() == comp_id && comp.tooltip().is_some());
+        if has_tooltip {
+            self.tooltip = Some(comp_id);
+            self.updates.push_back(Update::ShowTooltip(comp_id, point));
+        }
+    }
+
+    fn hide_tooltip(&mut self) {
+        if self.tooltip.take().is_some() {
+            self.updates.push_back(Update::HideTooltip);
+        }
+    }
+
+    // Starts the event loop if it is not running yet
+    fn start_loop(&mut self) {
+        if self.runtime.is_none() {
+            self.runtime = Some(Runtime::new().unwrap());
+            self.spawn_tasks();
+
+            if self.tooltip.is_none() {
+                self.schedule_tooltip();
+            }
+        }
+    }
+
+    // Spawn the streams of all components which are not running yet
+    fn spawn_tasks(&mut self) {
+        let runtime = match self.runtime {
+            Some(ref mut runtime) => runtime,
+            None => return,
+        };
+
+        let comps = self
+            .left
+            .iter_mut()
+            .chain(&mut self.center)
+            .chain(&mut self.right);
+        for comp in comps {
+            if self.tasks.contains_key(&comp.id()) {
+                continue;
+            }
+
             // Propagate events to main thread
-            let combined = combined.for_each(move |comp_id| {
-                events_tx.send(comp_id).unwrap();
-                Ok(())
+            let events_tx = self.events.0.clone();
+            let events = comp.stream().for_each(move |comp_id| {
+                events_tx.send(Message::Component(comp_id)).map_err(|_| ())
             });
 
-            // Iterate over all component events forever
-            tokio::run(combined);
-        });
+            // Stop the stream once the component is removed
+            let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+            let task = events.select(cancel_rx.then(|_| Ok(()))).then(|_| Ok(()));
+
+            runtime.spawn(task);
+            self.tasks.insert(comp.id(), cancel_tx);
+        }
+    }
+}
 
-        (bar_events_tx, events_rx)
+impl General {
+    fn load(config: &Config) -> Result<Self, Error> {
+        Ok(General {
+            height: config.height,
+            position: config.position,
+            background: config.background.clone().load()?,
+            border: config.border,
+            monitors: config.monitors.clone(),
+            tooltip_delay: config.tooltip_delay,
+        })
     }
 }
+
+// Read, parse and validate the configuration file
+fn parse_config<T: Read>(mut config_file: T) -> Result<Config, Error> {
+    let mut content = String::new();
+    config_file.read_to_string(&mut content)?;
+
+    let config: Config = serde_fmt::from_str(&content)?;
+    config.validate()?;
+
+    Ok(config)
+}
@@ -0,0 +1,230 @@
+use inotify::{Inotify, WatchMask};
+use serde::de::Deserialize;
+use tokio::prelude::*;
+
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::PathBuf;
+
+use crate::components::sysfs::{read_value, sorted_entries};
+use crate::components::template::Template;
+use crate::components::{
+    Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings, ComponentStream,
+    ComponentTrait,
+};
+use crate::event::{Event, MouseButton, MouseButtonState};
+
+const DEFAULT_SYS: &str = "/sys";
+const DEFAULT_FORMAT: &str = "{percent}%";
+const DEFAULT_STEP: u64 = 5;
+const DEFAULT_MIN: u64 = 1;
+const PLACEHOLDERS: &[&str] = &["percent", "brightness", "max"];
+
+pub struct Backlight {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    state: State,
+    write_error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_sys")]
+    sys: PathBuf,
+    device: Option<String>,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_step")]
+    step: u64,
+    #[serde(default = "default_min")]
+    min: u64,
+}
+
+fn default_sys() -> PathBuf {
+    PathBuf::from(DEFAULT_SYS)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_step() -> u64 {
+    DEFAULT_STEP
+}
+
+fn default_min() -> u64 {
+    DEFAULT_MIN
+}
+
+// Formatted brightness of the device
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    percent: Option<f64>,
+    error: Option<String>,
+}
+
+// Current and maximum brightness of a device
+struct Brightness {
+    current: u64,
+    max: u64,
+}
+
+impl ComponentTrait for Backlight {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.write_error
+            .clone()
+            .or_else(|| self.state.error.clone())
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.state.percent
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+
+        // Watch for changes to the brightness made by other applications
+        let (mut inotify, path) = match (Inotify::init(), self.find_device()) {
+            (Ok(inotify), Ok(path)) => (inotify, path),
+            _ => return Box::new(stream::empty()),
+        };
+        let watch_mask = WatchMask::MODIFY | WatchMask::CLOSE_WRITE;
+        if inotify
+            .add_watch(path.join("brightness"), watch_mask)
+            .is_err()
+        {
+            return Box::new(stream::empty());
+        }
+
+        let task = inotify.event_stream(vec![0; 1024]).map(move |_| id);
+        Box::new(task.map_err(|_| ()))
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn notify(&mut self, event: Event) -> bool {
+        match event {
+            Event::Click(button, MouseButtonState::Pressed, _) => {
+                let result = match button {
+                    MouseButton::WheelUp => self.adjust(true),
+                    MouseButton::WheelDown => self.adjust(false),
+                    _ => return false,
+                };
+
+                self.write_error = result.err().map(|err| err.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Backlight {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let format = Template::parse(&extra.format, PLACEHOLDERS)?;
+
+        let mut backlight = Self {
+            settings,
+            id: ComponentID::default(),
+            extra,
+            format,
+            state: State::default(),
+            write_error: None,
+        };
+        backlight.state = backlight.read_state();
+
+        Ok(Component::new(backlight))
+    }
+
+    // Get the configured device, or the first one if no device has been configured
+    fn find_device(&self) -> Result<PathBuf, IOError> {
+        let backlights = self.extra.sys.join("class/backlight");
+        match self.extra.device {
+            Some(ref device) => Ok(backlights.join(device)),
+            None => sorted_entries(&backlights)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| IOError::new(ErrorKind::NotFound, "no backlight found")),
+        }
+    }
+
+    fn read_brightness(&self) -> Result<(PathBuf, Brightness), IOError> {
+        let path = self.find_device()?;
+        let brightness = Brightness {
+            current: read_value(&path.join("brightness"))?,
+            max: read_value(&path.join("max_brightness"))?,
+        };
+        Ok((path, brightness))
+    }
+
+    fn read_state(&self) -> State {
+        match self.read_brightness() {
+            Ok((_, brightness)) => {
+                let percent = if brightness.max == 0 {
+                    0
+                } else {
+                    (brightness.current as f64 * 100. / brightness.max as f64).round() as u64
+                };
+
+                let values = [
+                    ("percent", percent.into()),
+                    ("brightness", brightness.current.into()),
+                    ("max", brightness.max.into()),
+                ];
+                State {
+                    text: self.format.render(&values),
+                    percent: Some(percent as f64),
+                    error: None,
+                }
+            }
+            Err(err) => State {
+                text: String::new(),
+                percent: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+
+    // Change the brightness by one step, without going below the minimum
+    fn adjust(&self, increase: bool) -> Result<(), IOError> {
+        let (path, brightness) = self.read_brightness()?;
+
+        let step = (brightness.max * self.extra.step / 100).max(1);
+        let min = (brightness.max * self.extra.min + 99) / 100;
+        let new = if increase {
+            brightness.current.saturating_add(step)
+        } else {
+            brightness.current.saturating_sub(step)
+        };
+
+        let new = new.max(min).min(brightness.max);
+        fs::write(path.join("brightness"), new.to_string())
+    }
+}
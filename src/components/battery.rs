@@ -0,0 +1,220 @@
+use serde::de::Deserialize;
+
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::components::sysfs::{read_string, read_value, sorted_entries};
+use crate::components::template::{DurationStyle, Template, UnitKind, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_ROOT: &str = "/sys/class/power_supply";
+const DEFAULT_FORMAT: &str = "{capacity}%";
+const DEFAULT_INTERVAL_MILLIS: u64 = 10000;
+const PLACEHOLDERS: &[&str] = &["capacity", "status", "time", "power"];
+const UNITS: &[(&str, UnitKind)] = &[("time", UnitKind::Duration)];
+
+pub struct Battery {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    state: State,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_root")]
+    root: PathBuf,
+    device: Option<String>,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_root() -> PathBuf {
+    PathBuf::from(DEFAULT_ROOT)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Formatted battery information
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    capacity: Option<f64>,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Battery {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.state.capacity
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Battery {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let mut battery = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse_with_units(&extra.format, PLACEHOLDERS, UNITS)?,
+            extra,
+            state: State::default(),
+        };
+        battery.state = battery.read_state();
+
+        Ok(Component::new(battery))
+    }
+
+    fn read_state(&self) -> State {
+        let info = match self.extra.device {
+            Some(ref device) => read_battery(&self.extra.root.join(device)),
+            None => find_battery(&self.extra.root).and_then(|path| read_battery(&path)),
+        };
+
+        match info {
+            Ok(info) => State {
+                text: self.format.render(&info.values()),
+                capacity: Some(info.capacity as f64),
+                error: None,
+            },
+            Err(err) => State {
+                text: String::new(),
+                capacity: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+// Current state of a single battery
+struct BatteryInfo {
+    capacity: u64,
+    status: String,
+    // Remaining time until the battery is empty or full in seconds
+    time: Option<u64>,
+    // Power consumption or charging rate in microwatts
+    power: Option<u64>,
+}
+
+impl BatteryInfo {
+    fn values(&self) -> Vec<(&'static str, Value)> {
+        let mut values = vec![
+            ("capacity", self.capacity.into()),
+            ("status", self.status.as_str().into()),
+        ];
+
+        // Both are unknown while the battery is neither charging nor discharging
+        if let Some(time) = self.time {
+            let time = Duration::from_secs(time);
+            values.push(("time", Value::Duration(time, DurationStyle::Hours)));
+        }
+        if let Some(power) = self.power {
+            values.push(("power", Value::Number(power as f64 / 1_000_000., 1)));
+        }
+
+        values
+    }
+}
+
+// Find the first power supply which is a battery
+fn find_battery(root: &Path) -> Result<PathBuf, IOError> {
+    sorted_entries(root)?
+        .into_iter()
+        .find(|path| {
+            read_string(&path.join("type"))
+                .map(|kind| kind == "Battery")
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| IOError::new(ErrorKind::NotFound, "no battery found"))
+}
+
+fn read_battery(path: &Path) -> Result<BatteryInfo, IOError> {
+    let status = read_string(&path.join("status"))?.to_lowercase();
+
+    // Batteries report either energy in µWh or charge in µAh
+    let (now, full, rate) = match read_value(&path.join("energy_now")) {
+        Ok(now) => (
+            now,
+            read_value(&path.join("energy_full"))?,
+            read_value(&path.join("power_now")).ok(),
+        ),
+        Err(_) => (
+            read_value(&path.join("charge_now"))?,
+            read_value(&path.join("charge_full"))?,
+            read_value(&path.join("current_now")).ok(),
+        ),
+    };
+
+    let capacity = match read_value(&path.join("capacity")) {
+        Ok(capacity) => capacity,
+        Err(_) if full > 0 => (now * 100 / full).min(100),
+        Err(err) => return Err(err),
+    };
+
+    let time = match (status.as_str(), rate) {
+        ("discharging", Some(rate)) if rate > 0 => Some(now * 3600 / rate),
+        ("charging", Some(rate)) if rate > 0 => Some(full.saturating_sub(now) * 3600 / rate),
+        _ => None,
+    };
+
+    // Convert the current to power if the battery does not report it directly
+    let power = match read_value(&path.join("power_now")) {
+        Ok(power) => Some(power),
+        Err(_) => match (rate, read_value(&path.join("voltage_now"))) {
+            (Some(current), Ok(voltage)) => Some(current * voltage / 1_000_000),
+            _ => None,
+        },
+    };
+
+    Ok(BatteryInfo {
+        capacity,
+        status,
+        time,
+        power,
+    })
+}
@@ -1,40 +1,69 @@
-#[cfg(all(feature = "json-fmt", not(feature = "toml-fmt")))]
-use serde_json as serde_fmt;
-#[cfg(not(any(feature = "toml-fmt", feature = "json-fmt")))]
-use serde_yaml as serde_fmt;
-#[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
-use toml as serde_fmt;
-
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, Locale, NaiveDate, TimeZone, Timelike,
+    Utc,
+};
+use chrono_tz::Tz;
 use serde::de::Deserialize;
 use tokio::prelude::*;
-use tokio::timer::Interval;
+use tokio::timer::Delay;
 
+use std::convert::TryFrom;
+use std::fmt::Display;
 use std::time::{Duration, Instant};
-use time;
 
 use crate::components::{
-    Component, ComponentID, ComponentSettings, ComponentStream, ComponentTrait,
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
 };
+use crate::event::{Event, MouseButton, MouseButtonState};
 
-const DEFAULT_INTERVAL_MILLIS: u64 = 15000;
+const DEFAULT_FORMAT: &str = "%H:%M";
 
 pub struct Clock {
     id: ComponentID,
     settings: ComponentSettings,
     extra: Extra,
+    zone: Zone,
+    locale: Option<Locale>,
+    precision: Precision,
+    alternate: bool,
+    text: String,
 }
 
 #[derive(Deserialize)]
 struct Extra {
     interval: Option<u64>,
+    #[serde(default = "default_format")]
+    format: String,
+    alt_format: Option<String>,
+    timezone: Option<String>,
+    locale: Option<String>,
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+// Timezone used for displaying the time
+#[derive(Copy, Clone)]
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+// Smallest unit of time displayed by a format
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Precision {
+    Second,
+    Minute,
+    Hour,
+    Day,
 }
 
 impl ComponentTrait for Clock {
     fn text(&self) -> String {
-        match time::now().strftime("%H:%M") {
-            Ok(time) => format!("{}", time),
-            _ => String::new(),
-        }
+        self.text.clone()
     }
 
     fn settings(&self) -> &ComponentSettings {
@@ -43,26 +72,200 @@ impl ComponentTrait for Clock {
 
     fn stream(&self) -> ComponentStream {
         let id = self.id();
-        let dur = Duration::from_millis(self.extra.interval.unwrap_or(DEFAULT_INTERVAL_MILLIS));
-        let task = Interval::new(Instant::now() + dur, dur).and_then(move |_| Ok(id));
+
+        if let Some(interval) = self.extra.interval {
+            return interval_stream(id, interval);
+        }
+
+        // Tick at the start of every unit displayed by the clock
+        let zone = self.zone;
+        let precision = self.precision;
+        let task = stream::repeat(())
+            .and_then(move |_| Delay::new(Instant::now() + zone.until_next(precision)))
+            .map(move |_| id);
         Box::new(task.map_err(|_| ()))
     }
 
     fn update(&mut self) -> bool {
+        self.text = self.format_now();
         true
     }
 
+    fn tooltip(&self) -> Option<String> {
+        let today = match self.zone {
+            Zone::Local => Local::now().naive_local().date(),
+            Zone::Named(tz) => Utc::now().with_timezone(&tz).naive_local().date(),
+        };
+        Some(calendar(today, self.locale))
+    }
+
+    fn notify(&mut self, event: Event) -> bool {
+        match event {
+            // Toggle between the primary and alternative format
+            Event::Click(MouseButton::Left, MouseButtonState::Released, _)
+                if self.extra.alt_format.is_some() =>
+            {
+                self.alternate = !self.alternate;
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn id(&self) -> ComponentID {
         self.id
     }
 }
 
 impl Clock {
-    pub(crate) fn create(settings: ComponentSettings, extra: serde_fmt::Value) -> Component {
-        Component(Box::new(Self {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+
+        let zone = match extra.timezone {
+            Some(ref timezone) => Zone::Named(timezone.parse::<Tz>()?),
+            None => Zone::Local,
+        };
+
+        let locale = match extra.locale {
+            Some(ref locale) => match Locale::try_from(locale.as_str()) {
+                Ok(locale) => Some(locale),
+                Err(_) => return Err(format!("unknown locale `{}`", locale).into()),
+            },
+            None => None,
+        };
+
+        let mut precision = format_precision(&extra.format)?;
+        if let Some(ref alt_format) = extra.alt_format {
+            precision = precision.min(format_precision(alt_format)?);
+        }
+
+        let mut clock = Self {
             settings,
             id: ComponentID::default(),
-            extra: Extra::deserialize(extra).unwrap(),
-        }))
+            extra,
+            zone,
+            locale,
+            precision,
+            alternate: false,
+            text: String::new(),
+        };
+        clock.text = clock.format_now();
+
+        Ok(Component::new(clock))
+    }
+
+    // Format the current time using the active format
+    fn format_now(&self) -> String {
+        let format = match self.extra.alt_format {
+            Some(ref alt_format) if self.alternate => alt_format,
+            _ => &self.extra.format,
+        };
+
+        match self.zone {
+            Zone::Local => format_time(Local::now(), format, self.locale),
+            Zone::Named(tz) => format_time(Utc::now().with_timezone(&tz), format, self.locale),
+        }
+    }
+}
+
+impl Zone {
+    // Duration until the next full unit of time in this timezone
+    fn until_next(self, precision: Precision) -> Duration {
+        let (seconds, millis) = match self {
+            Zone::Local => time_of_day(Local::now()),
+            Zone::Named(tz) => time_of_day(Utc::now().with_timezone(&tz)),
+        };
+
+        let unit = match precision {
+            Precision::Second => 1,
+            Precision::Minute => 60,
+            Precision::Hour => 3600,
+            Precision::Day => 86400,
+        };
+
+        let elapsed = (seconds % unit) * 1000 + millis;
+        Duration::from_millis(unit * 1000 - elapsed)
+    }
+}
+
+// Seconds since midnight and milliseconds since the last second
+fn time_of_day<T: TimeZone>(time: DateTime<T>) -> (u64, u64) {
+    // Leap seconds are represented as more than 1000 milliseconds
+    let millis = u64::from(time.nanosecond() / 1_000_000).min(999);
+    (u64::from(time.num_seconds_from_midnight()), millis)
+}
+
+fn format_time<T>(time: DateTime<T>, format: &str, locale: Option<Locale>) -> String
+where
+    T: TimeZone,
+    T::Offset: Display,
+{
+    match locale {
+        Some(locale) => time.format_localized(format, locale).to_string(),
+        None => time.format(format).to_string(),
+    }
+}
+
+// Calendar of the month containing a date, with weeks starting on Monday
+fn calendar(today: NaiveDate, locale: Option<Locale>) -> String {
+    let format = |date: NaiveDate, format| match locale {
+        Some(locale) => date.format_localized(format, locale).to_string(),
+        None => date.format(format).to_string(),
+    };
+
+    let first = today - ChronoDuration::days(i64::from(today.day0()));
+    let offset = first.weekday().num_days_from_monday();
+    let monday = first - ChronoDuration::days(i64::from(offset));
+
+    let weekdays = (0..7)
+        .map(|i| {
+            let weekday = format(monday + ChronoDuration::days(i), "%a");
+            format!("{:>2}", weekday.chars().take(2).collect::<String>())
+        })
+        .collect::<Vec<_>>();
+    let mut lines = vec![
+        format!("{:^20}", format(today, "%B %Y")),
+        weekdays.join(" "),
+    ];
+
+    // Days of the previous month are left blank
+    let mut days = vec![String::from("  "); offset as usize];
+    let mut day = first;
+    while day.month() == today.month() {
+        days.push(format!("{:>2}", day.day()));
+        day += ChronoDuration::days(1);
+    }
+    lines.extend(days.chunks(7).map(|week| week.join(" ")));
+
+    lines.join("\n")
+}
+
+// Find the smallest unit of time in a format, rejecting invalid formats
+fn format_precision(format: &str) -> Result<Precision, ComponentError> {
+    let mut precision = Precision::Day;
+    for item in StrftimeItems::new(format) {
+        let item_precision = match item {
+            Item::Error => return Err(format!("invalid time format `{}`", format).into()),
+            Item::Numeric(Numeric::Second, _)
+            | Item::Numeric(Numeric::Nanosecond, _)
+            | Item::Numeric(Numeric::Timestamp, _)
+            | Item::Fixed(Fixed::Nanosecond)
+            | Item::Fixed(Fixed::Nanosecond3)
+            | Item::Fixed(Fixed::Nanosecond6)
+            | Item::Fixed(Fixed::Nanosecond9)
+            | Item::Fixed(Fixed::RFC2822)
+            | Item::Fixed(Fixed::RFC3339) => Precision::Second,
+            Item::Numeric(Numeric::Minute, _) => Precision::Minute,
+            Item::Numeric(Numeric::Hour, _)
+            | Item::Numeric(Numeric::Hour12, _)
+            | Item::Fixed(Fixed::LowerAmPm)
+            | Item::Fixed(Fixed::UpperAmPm) => Precision::Hour,
+            _ => Precision::Day,
+        };
+        precision = precision.min(item_precision);
     }
+    Ok(precision)
 }
@@ -0,0 +1,183 @@
+use serde::de::Deserialize;
+use tokio::prelude::*;
+use tokio::timer::Interval;
+use tokio_process::CommandExt;
+
+use std::process::{Command as ProcessCommand, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::components::{
+    restart_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait, RefreshStream,
+};
+
+const DEFAULT_SHELL: &str = "sh";
+
+pub struct Command {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    state: State,
+    shared_state: Arc<Mutex<State>>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Extra {
+    exec: String,
+    interval: Option<u64>,
+    #[serde(default = "default_shell")]
+    shell: String,
+    timeout: Option<u64>,
+    #[serde(default)]
+    restart_on_exit: bool,
+}
+
+pub(crate) fn default_shell() -> String {
+    String::from(DEFAULT_SHELL)
+}
+
+// Result of the last command execution
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Command {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        self.stream_with_refresh(Box::new(stream::empty()))
+    }
+
+    fn stream_with_refresh(&self, refresh: RefreshStream) -> ComponentStream {
+        let id = self.id();
+        let extra = self.extra.clone();
+        let shared_state = self.shared_state.clone();
+
+        // Run immediately and then repeat based on the interval or process exit
+        let runs: Box<dyn Stream<Item = (), Error = ()> + Send> = match extra.interval {
+            Some(interval) => {
+                let interval = Interval::new(Instant::now(), Duration::from_millis(interval));
+                Box::new(interval.map(|_| ()).map_err(|_| ()))
+            }
+            None if extra.restart_on_exit => {
+                // Delay every restart, so commands exiting right away do not run in a busy loop
+                Box::new(restart_stream(|| stream::once(Ok(()))))
+            }
+            None => Box::new(stream::once(Ok(()))),
+        };
+
+        // Refresh requests run the command again right away
+        let task = runs
+            .select(refresh)
+            .and_then(move |_| run(&extra))
+            .filter_map(move |new_state| {
+                let mut state = shared_state.lock().unwrap();
+
+                // Only request an update if the output has changed
+                if new_state != *state {
+                    *state = new_state;
+                    Some(id)
+                } else {
+                    None
+                }
+            });
+
+        Box::new(task)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.shared_state.lock().unwrap();
+        if *state != self.state {
+            self.state = state.clone();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Command {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        Ok(Component::new(Self {
+            settings,
+            id: ComponentID::default(),
+            extra: Extra::deserialize(extra)?,
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(State::default())),
+        }))
+    }
+}
+
+// Run the command once and convert its output to the new state
+fn run(extra: &Extra) -> Box<dyn Future<Item = State, Error = ()> + Send> {
+    let output = ProcessCommand::new(&extra.shell)
+        .arg("-c")
+        .arg(&extra.exec)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output_async();
+
+    match extra.timeout {
+        Some(timeout) => {
+            let duration = Duration::from_millis(timeout);
+            let output = output
+                .timeout(duration)
+                .map_err(move |err| match err.into_inner() {
+                    Some(err) => err.to_string(),
+                    None => format!("command timed out after {}ms", timeout),
+                });
+            Box::new(output.then(state_from_output))
+        }
+        None => Box::new(
+            output
+                .map_err(|err| err.to_string())
+                .then(state_from_output),
+        ),
+    }
+}
+
+fn state_from_output(output: Result<Output, String>) -> Result<State, ()> {
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            return Ok(State {
+                text: String::new(),
+                error: Some(err),
+            })
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let text = stdout.lines().next().unwrap_or("").to_owned();
+
+    let error = if output.status.success() {
+        None
+    } else {
+        match output.status.code() {
+            Some(code) => Some(format!("command exited with status {}", code)),
+            None => Some(String::from("command was terminated by a signal")),
+        }
+    };
+
+    Ok(State { text, error })
+}
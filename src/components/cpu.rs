@@ -0,0 +1,234 @@
+use serde::de::Deserialize;
+
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::components::template::{Template, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_PROC: &str = "/proc";
+const DEFAULT_FORMAT: &str = "{usage}%";
+const DEFAULT_INTERVAL_MILLIS: u64 = 2000;
+const PLACEHOLDERS: &[&str] = &["usage", "max", "cores"];
+
+pub struct Cpu {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    sample: Vec<CpuTimes>,
+    usage: Option<f64>,
+    // Usage of every core during the last sample
+    cores: Vec<f64>,
+    text: String,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_proc")]
+    proc: PathBuf,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_proc() -> PathBuf {
+    PathBuf::from(DEFAULT_PROC)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Time spent by a CPU since boot in jiffies
+#[derive(Copy, Clone, Default)]
+struct CpuTimes {
+    idle: u64,
+    total: u64,
+}
+
+impl ComponentTrait for Cpu {
+    fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.usage
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        if self.cores.is_empty() {
+            return None;
+        }
+
+        let lines = self
+            .cores
+            .iter()
+            .enumerate()
+            .map(|(i, core)| format!("core{}: {:.0}%", i, core))
+            .collect::<Vec<_>>();
+        Some(lines.join("\n"))
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let (text, error) = self.sample();
+
+        // Only redraw when the rounded values have changed
+        if text != self.text || error != self.error {
+            self.text = text;
+            self.error = error;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Cpu {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+
+        // Every core has its own placeholder like `{core0}`
+        let cores = read_stat(&extra.proc.join("stat")).map_or(0, |sample| sample.len() - 1);
+        let core_names = (0..cores).map(|i| format!("core{}", i)).collect::<Vec<_>>();
+        let mut placeholders = PLACEHOLDERS.to_vec();
+        placeholders.extend(core_names.iter().map(String::as_str));
+
+        let mut cpu = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse(&extra.format, &placeholders)?,
+            extra,
+            sample: Vec::new(),
+            usage: None,
+            cores: Vec::new(),
+            text: String::new(),
+            error: None,
+        };
+
+        // Without a previous sample, the first value is the average since boot
+        let (text, error) = cpu.sample();
+        cpu.text = text;
+        cpu.error = error;
+
+        Ok(Component::new(cpu))
+    }
+
+    // Read the CPU times and format the usage since the last sample
+    fn sample(&mut self) -> (String, Option<String>) {
+        let sample = match read_stat(&self.extra.proc.join("stat")) {
+            Ok(sample) => sample,
+            Err(err) => {
+                self.usage = None;
+                self.cores.clear();
+                return (String::new(), Some(err.to_string()));
+            }
+        };
+
+        let usages = sample
+            .iter()
+            .enumerate()
+            .map(|(i, current)| {
+                let previous = self.sample.get(i).cloned().unwrap_or_default();
+                usage(previous, *current)
+            })
+            .collect::<Vec<_>>();
+        self.sample = sample;
+        self.usage = Some(usages[0]);
+
+        let cores = &usages[1..];
+        self.cores = cores.to_vec();
+        let max = cores.iter().cloned().fold(0., f64::max);
+
+        let mut values = vec![
+            ("usage", Value::Number(usages[0], 0)),
+            ("max", Value::Number(max, 0)),
+        ];
+        let core_names = (0..cores.len())
+            .map(|i| format!("core{}", i))
+            .collect::<Vec<_>>();
+        for (name, core) in core_names.iter().zip(cores) {
+            values.push((name, Value::Number(*core, 0)));
+        }
+        let all_cores = cores
+            .iter()
+            .map(|core| format!("{:.0}", core))
+            .collect::<Vec<_>>();
+        values.push(("cores", all_cores.join(" ").into()));
+
+        (self.format.render(&values), None)
+    }
+}
+
+// Read the times of all CPUs, starting with the sum of all cores
+fn read_stat(path: &Path) -> Result<Vec<CpuTimes>, IOError> {
+    let stat = fs::read_to_string(path)?;
+
+    let sample = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(|line| {
+            // Guest time is already included in the user time
+            let times = line
+                .split_whitespace()
+                .skip(1)
+                .take(8)
+                .map(|time| time.parse::<u64>().unwrap_or(0))
+                .collect::<Vec<_>>();
+
+            // Waiting for I/O is counted as idle time
+            let idle = times.get(3).cloned().unwrap_or(0) + times.get(4).cloned().unwrap_or(0);
+            CpuTimes {
+                idle,
+                total: times.iter().sum(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if sample.is_empty() {
+        return Err(IOError::new(ErrorKind::InvalidData, "no cpu found in stat"));
+    }
+
+    Ok(sample)
+}
+
+// CPU usage between two samples in percent
+fn usage(previous: CpuTimes, current: CpuTimes) -> f64 {
+    let total = current.total.saturating_sub(previous.total);
+    let idle = current.idle.saturating_sub(previous.idle);
+
+    if total == 0 {
+        0.
+    } else {
+        total.saturating_sub(idle) as f64 * 100. / total as f64
+    }
+}
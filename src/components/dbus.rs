@@ -0,0 +1,626 @@
+// Minimal D-Bus client
+//
+// Only the parts of the protocol necessary for calling methods with string arguments and
+// receiving replies and signals are supported. Messages are always sent in little endian.
+
+use futures::sync::mpsc::{self, UnboundedSender};
+use tokio::io::{read_exact, read_until, write_all, AsyncRead};
+use tokio::net::UnixStream;
+use tokio::prelude::*;
+use tokio::reactor::Handle;
+
+use std::env;
+use std::ffi::OsStr;
+use std::io::{BufReader, Error as IOError, ErrorKind};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::net::UnixStream as StdUnixStream;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+pub(crate) const METHOD_RETURN: u8 = 2;
+pub(crate) const ERROR: u8 = 3;
+pub(crate) const SIGNAL: u8 = 4;
+
+pub(crate) const BUS_NAME: &str = "org.freedesktop.DBus";
+pub(crate) const BUS_PATH: &str = "/org/freedesktop/DBus";
+pub(crate) const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+const METHOD_CALL: u8 = 1;
+const FIXED_HEADER_LEN: usize = 16;
+
+// Maximum size of a message and maximum nesting of containers allowed by the specification
+const MAX_MESSAGE_LEN: usize = 1 << 27;
+const MAX_DEPTH: usize = 64;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SENDER: u8 = 7;
+const FIELD_SIGNATURE: u8 = 8;
+
+// Value of any D-Bus type, with all integers widened to 64 bits
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Value {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Double(f64),
+    Str(String),
+    Array(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Struct(Vec<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unwrap_variant() {
+            Value::Str(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self.unwrap_variant() {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&[(Value, Value)]> {
+        match self.unwrap_variant() {
+            Value::Dict(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    fn unwrap_variant(&self) -> &Value {
+        match self {
+            Value::Variant(value) => value.unwrap_variant(),
+            value => value,
+        }
+    }
+}
+
+// Reply, error or signal received from the bus
+pub(crate) struct Message {
+    pub kind: u8,
+    pub reply_serial: Option<u32>,
+    pub sender: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub body: Vec<Value>,
+}
+
+// Handle for sending method calls over an established connection
+#[derive(Clone)]
+pub(crate) struct Connection {
+    sender: UnboundedSender<Vec<u8>>,
+    serial: Arc<AtomicU32>,
+}
+
+impl Connection {
+    // Queue a method call, returning the serial its reply will refer to
+    pub fn call(
+        &self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: &[&str],
+    ) -> u32 {
+        let serial = self.serial.fetch_add(1, Ordering::Relaxed);
+        let message = method_call(serial, destination, path, interface, member, args);
+        let _ = self.sender.unbounded_send(message);
+        serial
+    }
+}
+
+// Address of the session bus
+pub(crate) fn session_address() -> Option<String> {
+    env::var("DBUS_SESSION_BUS_ADDRESS").ok()
+}
+
+// Connect and authenticate to a bus, returning a connection and all received messages
+//
+// This must be called from within the tokio runtime, since sending is done by a separate task.
+pub(crate) fn connect(
+    address: &str,
+) -> impl Future<Item = (Connection, impl Stream<Item = Message, Error = IOError>), Error = IOError>
+{
+    let socket =
+        connect_socket(address).and_then(|socket| UnixStream::from_std(socket, &Handle::default()));
+    let (reader, writer) = match socket {
+        Ok(socket) => socket.split(),
+        Err(err) => return future::Either::A(future::err(err)),
+    };
+
+    // Authenticate as the user running the bar
+    let uid = unsafe { libc::getuid() }.to_string();
+    let uid = uid
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    let auth = format!("\0AUTH EXTERNAL {}\r\n", uid).into_bytes();
+
+    let connection = write_all(writer, auth)
+        .and_then(|(writer, _)| {
+            read_until(BufReader::new(reader), b'\n', Vec::new()).map(|(r, line)| (r, writer, line))
+        })
+        .and_then(|(reader, writer, line)| {
+            if !line.starts_with(b"OK") {
+                return Err(IOError::new(
+                    ErrorKind::PermissionDenied,
+                    "D-Bus authentication failed",
+                ));
+            }
+
+            // Every connection has to start by saying hello
+            let mut begin = b"BEGIN\r\n".to_vec();
+            begin.append(&mut method_call(
+                1,
+                BUS_NAME,
+                BUS_PATH,
+                BUS_NAME,
+                "Hello",
+                &[],
+            ));
+
+            let (sender, receiver) = mpsc::unbounded();
+            let _ = sender.unbounded_send(begin);
+            let task = receiver
+                .fold(writer, |writer, message| {
+                    write_all(writer, message).map(|(w, _)| w).map_err(|_| ())
+                })
+                .map(|_| ());
+            tokio::spawn(task);
+
+            let connection = Connection {
+                sender,
+                serial: Arc::new(AtomicU32::new(2)),
+            };
+            let messages = stream::unfold(reader, |reader| {
+                Some(read_message(reader).map(|(reader, message)| (message, reader)))
+            });
+            Ok((connection, messages))
+        });
+
+    future::Either::B(connection)
+}
+
+// Connect to the first supported unix socket address
+fn connect_socket(address: &str) -> Result<StdUnixStream, IOError> {
+    for entry in address.split(';') {
+        let options = match entry.strip_prefix("unix:") {
+            Some(options) => options,
+            None => continue,
+        };
+
+        for option in options.split(',') {
+            if let Some(path) = option.strip_prefix("path=") {
+                return StdUnixStream::connect(OsStr::from_bytes(&unescape(path)));
+            } else if let Some(name) = option.strip_prefix("abstract=") {
+                return connect_abstract(&unescape(name));
+            }
+        }
+    }
+
+    Err(IOError::new(
+        ErrorKind::InvalidInput,
+        "unsupported D-Bus address",
+    ))
+}
+
+// Connect to a socket in the abstract namespace, which starts with a null byte
+fn connect_abstract(name: &[u8]) -> Result<StdUnixStream, IOError> {
+    let mut address: libc::sockaddr_un = unsafe { mem::zeroed() };
+    address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    if name.len() >= address.sun_path.len() {
+        return Err(IOError::new(
+            ErrorKind::InvalidInput,
+            "D-Bus address too long",
+        ));
+    }
+    for (dst, src) in address.sun_path[1..].iter_mut().zip(name) {
+        *dst = *src as _;
+    }
+    let len = mem::size_of::<libc::sa_family_t>() + 1 + name.len();
+
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(IOError::last_os_error());
+    }
+    let socket = unsafe { StdUnixStream::from_raw_fd(fd) };
+
+    let result = unsafe {
+        libc::connect(
+            fd,
+            &address as *const libc::sockaddr_un as *const libc::sockaddr,
+            len as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(IOError::last_os_error());
+    }
+
+    Ok(socket)
+}
+
+// Decode the percent escapes of an address value
+fn unescape(value: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut chars = value.bytes();
+    while let Some(byte) = chars.next() {
+        let escaped = if byte == b'%' {
+            let hex = [chars.next().unwrap_or(0), chars.next().unwrap_or(0)];
+            std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        bytes.push(escaped.unwrap_or(byte));
+    }
+    bytes
+}
+
+fn read_message<R: AsyncRead>(reader: R) -> impl Future<Item = (R, Message), Error = IOError> {
+    read_exact(reader, [0; FIXED_HEADER_LEN]).and_then(|(reader, fixed)| {
+        let big_endian = match fixed[0] {
+            b'l' => false,
+            b'B' => true,
+            _ => return future::Either::A(future::err(invalid("invalid D-Bus message"))),
+        };
+        let read_u32 = |offset: usize| {
+            let bytes = [
+                fixed[offset],
+                fixed[offset + 1],
+                fixed[offset + 2],
+                fixed[offset + 3],
+            ];
+            if big_endian {
+                u32::from_be_bytes(bytes) as usize
+            } else {
+                u32::from_le_bytes(bytes) as usize
+            }
+        };
+
+        // Lengths are checked before any of them are added, to never allocate too much memory
+        let body_len = read_u32(4);
+        let fields_len = read_u32(12);
+        if body_len > MAX_MESSAGE_LEN || fields_len > MAX_MESSAGE_LEN {
+            return future::Either::A(future::err(invalid("D-Bus message too long")));
+        }
+
+        // The header fields are padded to a multiple of eight bytes
+        let header_len = align(FIXED_HEADER_LEN + fields_len, 8);
+        if header_len + body_len > MAX_MESSAGE_LEN {
+            return future::Either::A(future::err(invalid("D-Bus message too long")));
+        }
+
+        let remaining = vec![0; header_len + body_len - FIXED_HEADER_LEN];
+        let message = read_exact(reader, remaining).and_then(move |(reader, remaining)| {
+            let mut data = fixed.to_vec();
+            data.extend_from_slice(&remaining);
+            let message = parse_message(&data, header_len)?;
+            Ok((reader, message))
+        });
+        future::Either::B(message)
+    })
+}
+
+fn parse_message(data: &[u8], header_len: usize) -> Result<Message, IOError> {
+    let mut parser = Parser {
+        data,
+        pos: 12,
+        big_endian: data[0] == b'B',
+    };
+
+    let mut message = Message {
+        kind: data[1],
+        reply_serial: None,
+        sender: None,
+        interface: None,
+        member: None,
+        error_name: None,
+        body: Vec::new(),
+    };
+
+    let mut signature = String::new();
+    let fields = parser.value(b"a(yv)", 0)?;
+    for field in fields.as_array().unwrap_or_default() {
+        let (code, value) = match field {
+            Value::Struct(field) if field.len() == 2 => (&field[0], &field[1]),
+            _ => continue,
+        };
+
+        let string = value.as_str().map(str::to_owned);
+        match (code, value) {
+            (Value::Uint(code), _) if *code == u64::from(FIELD_INTERFACE) => {
+                message.interface = string
+            }
+            (Value::Uint(code), _) if *code == u64::from(FIELD_MEMBER) => message.member = string,
+            (Value::Uint(code), _) if *code == u64::from(FIELD_ERROR_NAME) => {
+                message.error_name = string
+            }
+            (Value::Uint(code), _) if *code == u64::from(FIELD_SENDER) => message.sender = string,
+            (Value::Uint(code), _) if *code == u64::from(FIELD_SIGNATURE) => {
+                signature = string.unwrap_or_default()
+            }
+            (Value::Uint(code), Value::Variant(value))
+                if *code == u64::from(FIELD_REPLY_SERIAL) =>
+            {
+                if let Value::Uint(serial) = **value {
+                    message.reply_serial = Some(serial as u32);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    parser.pos = header_len;
+    let mut signature = signature.as_bytes();
+    while !signature.is_empty() {
+        let (single, rest) = split_type(signature)?;
+        message.body.push(parser.value(single, 0)?);
+        signature = rest;
+    }
+
+    Ok(message)
+}
+
+// Split the first complete type from a signature
+fn split_type(signature: &[u8]) -> Result<(&[u8], &[u8]), IOError> {
+    let error = || invalid("invalid D-Bus signature");
+
+    let len = match signature.first().ok_or_else(error)? {
+        b'a' => 1 + split_type(&signature[1..])?.0.len(),
+        b'(' | b'{' => {
+            let close = if signature[0] == b'(' { b')' } else { b'}' };
+            let mut len = 1;
+            while signature.get(len) != Some(&close) {
+                len += split_type(&signature[len..])?.0.len();
+            }
+
+            // Structs must not be empty, which also guarantees progress while parsing them
+            if len == 1 {
+                return Err(error());
+            }
+            len + 1
+        }
+        b')' | b'}' => return Err(error()),
+        _ => 1,
+    };
+
+    Ok(signature.split_at(len))
+}
+
+fn invalid(message: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidData, message)
+}
+
+// Reader for values in the D-Bus wire format
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, signature: &[u8], depth: usize) -> Result<Value, IOError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("D-Bus value nested too deeply"));
+        }
+
+        let value = match signature[0] {
+            b'y' => Value::Uint(u64::from(self.bytes(1)?[0])),
+            b'b' => Value::Bool(self.u32()? != 0),
+            b'n' => Value::Int(i64::from(self.u16()? as i16)),
+            b'q' => Value::Uint(u64::from(self.u16()?)),
+            b'i' => Value::Int(i64::from(self.u32()? as i32)),
+            b'u' | b'h' => Value::Uint(u64::from(self.u32()?)),
+            b'x' => Value::Int(self.u64()? as i64),
+            b't' => Value::Uint(self.u64()?),
+            b'd' => Value::Double(f64::from_bits(self.u64()?)),
+            b's' | b'o' => {
+                let len = self.u32()? as usize;
+                let string = self.string(len)?;
+                Value::Str(string)
+            }
+            b'g' => {
+                let len = usize::from(self.bytes(1)?[0]);
+                Value::Str(self.string(len)?)
+            }
+            b'v' => {
+                let len = usize::from(self.bytes(1)?[0]);
+                let signature = self.string(len)?;
+                let (single, _) = split_type(signature.as_bytes())?;
+                Value::Variant(Box::new(self.value(single, depth + 1)?))
+            }
+            b'a' => {
+                let len = self.u32()? as usize;
+                let element = &signature[1..];
+                self.align(alignment(element[0]))?;
+
+                let end = self.pos + len;
+                if end > self.data.len() {
+                    return Err(invalid("truncated D-Bus message"));
+                }
+
+                // Every element takes at least one byte, so the end is always reached
+                let mut values = Vec::new();
+                while self.pos < end {
+                    values.push(self.value(element, depth + 1)?);
+                }
+
+                // Dictionaries are arrays of key-value pairs
+                if element[0] == b'{' {
+                    let entries = values.into_iter().filter_map(|entry| match entry {
+                        Value::Struct(mut pair) if pair.len() == 2 => {
+                            let value = pair.pop()?;
+                            Some((pair.pop()?, value))
+                        }
+                        _ => None,
+                    });
+                    Value::Dict(entries.collect())
+                } else {
+                    Value::Array(values)
+                }
+            }
+            b'(' | b'{' => {
+                self.align(8)?;
+                let mut fields = Vec::new();
+                let mut inner = &signature[1..signature.len() - 1];
+                while !inner.is_empty() {
+                    let (single, rest) = split_type(inner)?;
+                    fields.push(self.value(single, depth + 1)?);
+                    inner = rest;
+                }
+                Value::Struct(fields)
+            }
+            _ => return Err(invalid("unsupported D-Bus type")),
+        };
+
+        Ok(value)
+    }
+
+    fn align(&mut self, alignment: usize) -> Result<(), IOError> {
+        self.pos = align(self.pos, alignment);
+        if self.pos > self.data.len() {
+            return Err(invalid("truncated D-Bus message"));
+        }
+        Ok(())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8], IOError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("truncated D-Bus message"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, IOError> {
+        let string = String::from_utf8_lossy(self.bytes(len)?).into_owned();
+
+        // Skip the terminating null byte
+        self.bytes(1)?;
+
+        Ok(string)
+    }
+
+    fn u16(&mut self) -> Result<u16, IOError> {
+        self.align(2)?;
+        let big_endian = self.big_endian;
+        let bytes = self.bytes(2)?;
+        let bytes = [bytes[0], bytes[1]];
+        Ok(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, IOError> {
+        self.align(4)?;
+        let big_endian = self.big_endian;
+        let bytes = self.bytes(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn u64(&mut self) -> Result<u64, IOError> {
+        self.align(8)?;
+        let big_endian = self.big_endian;
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(if big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+}
+
+// Encode a method call with string arguments
+fn method_call(
+    serial: u32,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    member: &str,
+    args: &[&str],
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    for arg in args {
+        put_string(&mut body, arg);
+    }
+
+    let mut message = vec![b'l', METHOD_CALL, 0, 1];
+    message.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    message.extend_from_slice(&serial.to_le_bytes());
+
+    // The length of the header fields is only known once they have been written
+    message.extend_from_slice(&[0; 4]);
+    let mut put_field = |code: u8, kind: u8, value: &str| {
+        pad(&mut message, 8);
+        message.extend_from_slice(&[code, 1, kind, 0]);
+        if kind == b'g' {
+            message.push(value.len() as u8);
+            message.extend_from_slice(value.as_bytes());
+            message.push(0);
+        } else {
+            put_string(&mut message, value);
+        }
+    };
+    put_field(FIELD_PATH, b'o', path);
+    put_field(FIELD_INTERFACE, b's', interface);
+    put_field(FIELD_MEMBER, b's', member);
+    put_field(FIELD_DESTINATION, b's', destination);
+    if !args.is_empty() {
+        put_field(FIELD_SIGNATURE, b'g', &"s".repeat(args.len()));
+    }
+
+    let fields_len = (message.len() - FIXED_HEADER_LEN) as u32;
+    message[12..FIXED_HEADER_LEN].copy_from_slice(&fields_len.to_le_bytes());
+
+    pad(&mut message, 8);
+    message.append(&mut body);
+    message
+}
+
+fn put_string(buffer: &mut Vec<u8>, string: &str) {
+    pad(buffer, 4);
+    buffer.extend_from_slice(&(string.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(string.as_bytes());
+    buffer.push(0);
+}
+
+fn pad(buffer: &mut Vec<u8>, alignment: usize) {
+    buffer.resize(align(buffer.len(), alignment), 0);
+}
+
+// Alignment of a type by its first signature character
+fn alignment(kind: u8) -> usize {
+    match kind {
+        b'n' | b'q' => 2,
+        b'b' | b'i' | b'u' | b'h' | b's' | b'o' | b'a' => 4,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 1,
+    }
+}
+
+fn align(len: usize, alignment: usize) -> usize {
+    (len + alignment - 1) & !(alignment - 1)
+}
@@ -0,0 +1,296 @@
+use serde::de::Deserialize;
+
+use std::ffi::CString;
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::components::template::{Template, UnitBase, UnitKind, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_PROC: &str = "/proc";
+const DEFAULT_MOUNT: &str = "/";
+const DEFAULT_FORMAT: &str = "{mount} {percent}%";
+const DEFAULT_SEPARATOR: &str = " ";
+const DEFAULT_INTERVAL_MILLIS: u64 = 30000;
+const PLACEHOLDERS: &[&str] = &["mount", "fs", "used", "free", "total", "percent"];
+const UNITS: &[(&str, UnitKind)] = &[
+    ("used", UnitKind::Bytes),
+    ("free", UnitKind::Bytes),
+    ("total", UnitKind::Bytes),
+];
+
+pub struct Disk {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    state: State,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default)]
+    mounts: Vec<PathBuf>,
+    #[serde(default)]
+    discover: bool,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default = "default_proc")]
+    proc: PathBuf,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_separator")]
+    separator: String,
+    #[serde(default)]
+    base: UnitBase,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_proc() -> PathBuf {
+    PathBuf::from(DEFAULT_PROC)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_separator() -> String {
+    String::from(DEFAULT_SEPARATOR)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Formatted usage of all mount points
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+// Entry of the mount table
+struct Mount {
+    source: String,
+    path: PathBuf,
+    fs: String,
+}
+
+impl ComponentTrait for Disk {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Disk {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let mut disk = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse_with_units(&extra.format, PLACEHOLDERS, UNITS)?,
+            extra,
+            state: State::default(),
+        };
+        disk.state = disk.read_state();
+
+        Ok(Component::new(disk))
+    }
+
+    fn read_state(&self) -> State {
+        let mounts = match self.mounts() {
+            Ok(mounts) => mounts,
+            Err(err) => {
+                return State {
+                    text: String::new(),
+                    error: Some(err.to_string()),
+                }
+            }
+        };
+
+        // Mount points which could not be read are skipped
+        let mut texts = Vec::new();
+        let mut error = None;
+        for mount in mounts {
+            match statvfs(&mount.path) {
+                Ok(usage) => texts.push(self.format_mount(&mount, &usage)),
+                Err(err) => error = Some(format!("{}: {}", mount.path.display(), err)),
+            }
+        }
+
+        State {
+            text: texts.join(&self.extra.separator),
+            error,
+        }
+    }
+
+    // All mount points which should be displayed
+    fn mounts(&self) -> Result<Vec<Mount>, IOError> {
+        let table = read_mounts(&self.extra.proc.join("mounts"));
+
+        if !self.extra.discover {
+            let table = table.unwrap_or_default();
+            let paths = if self.extra.mounts.is_empty() {
+                vec![PathBuf::from(DEFAULT_MOUNT)]
+            } else {
+                self.extra.mounts.clone()
+            };
+
+            // Explicit mount points are used even if they are not in the mount table
+            let mounts = paths
+                .into_iter()
+                .map(|path| {
+                    let entry = table.iter().rev().find(|mount| mount.path == path);
+                    Mount {
+                        source: entry.map(|mount| mount.source.clone()).unwrap_or_default(),
+                        fs: entry.map(|mount| mount.fs.clone()).unwrap_or_default(),
+                        path,
+                    }
+                })
+                .collect();
+            return Ok(mounts);
+        }
+
+        let mounts = table?
+            .into_iter()
+            .filter(|mount| self.is_discovered(mount))
+            .collect();
+        Ok(mounts)
+    }
+
+    // Check if a mount from the mount table passes all filters
+    fn is_discovered(&self, mount: &Mount) -> bool {
+        let matches = |filters: &[String]| filters.iter().any(|f| filter_matches(f, mount));
+
+        // Without any filters, only block devices are included
+        let included = if self.extra.include.is_empty() {
+            mount.source.starts_with('/')
+        } else {
+            matches(&self.extra.include)
+        };
+
+        included && !matches(&self.extra.exclude)
+    }
+
+    fn format_mount(&self, mount: &Mount, usage: &Usage) -> String {
+        let base = self.extra.base;
+        let used_and_free = usage.used + usage.free;
+        let percent = if used_and_free == 0 {
+            0
+        } else {
+            (usage.used as f64 * 100. / used_and_free as f64).round() as u64
+        };
+
+        let values = [
+            ("mount", mount.path.display().to_string().into()),
+            ("fs", mount.fs.as_str().into()),
+            ("used", Value::Bytes(usage.used, base)),
+            ("free", Value::Bytes(usage.free, base)),
+            ("total", Value::Bytes(usage.total, base)),
+            ("percent", percent.into()),
+        ];
+        self.format.render(&values)
+    }
+}
+
+// Check if a filter matches the mount point or filesystem type of a mount
+//
+// A trailing `*` matches every mount point starting with the filter.
+fn filter_matches(filter: &str, mount: &Mount) -> bool {
+    match filter.strip_suffix('*') {
+        Some(prefix) => mount.path.to_string_lossy().starts_with(prefix),
+        None => mount.fs == filter || mount.path == Path::new(filter),
+    }
+}
+
+fn read_mounts(path: &Path) -> Result<Vec<Mount>, IOError> {
+    let mounts = fs::read_to_string(path)?;
+
+    let mounts = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(Mount {
+                source: unescape(fields.next()?),
+                path: PathBuf::from(unescape(fields.next()?)),
+                fs: fields.next()?.to_owned(),
+            })
+        })
+        .collect();
+    Ok(mounts)
+}
+
+// Replace the octal escapes used for whitespace in the mount table
+fn unescape(field: &str) -> String {
+    field
+        .replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+// Used and available space of a filesystem in bytes
+struct Usage {
+    used: u64,
+    free: u64,
+    total: u64,
+}
+
+fn statvfs(path: &Path) -> Result<Usage, IOError> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| IOError::new(ErrorKind::InvalidInput, err))?;
+
+    let stat = unsafe {
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(IOError::last_os_error());
+        }
+        stat.assume_init()
+    };
+
+    let block_size = stat.f_frsize as u64;
+    let total = stat.f_blocks as u64 * block_size;
+    Ok(Usage {
+        used: total.saturating_sub(stat.f_bfree as u64 * block_size),
+        free: stat.f_bavail as u64 * block_size,
+        total,
+    })
+}
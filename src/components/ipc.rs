@@ -0,0 +1,191 @@
+// Client for the IPC protocol shared by i3 and sway
+//
+// Every message starts with the `i3-ipc` magic string, followed by the length and type of the
+// payload as native endian 32 bit integers.
+
+use tokio::io::{read_exact, write_all};
+use tokio::net::UnixStream;
+use tokio::prelude::*;
+
+use std::env;
+use std::io::{Error as IOError, ErrorKind};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::components::{json, restart_stream};
+use crate::config::Monitor;
+
+pub(crate) const RUN_COMMAND: u32 = 0;
+pub(crate) const GET_WORKSPACES: u32 = 1;
+pub(crate) const SUBSCRIBE: u32 = 2;
+pub(crate) const GET_TREE: u32 = 4;
+
+const MAGIC: &[u8] = b"i3-ipc";
+const HEADER_LEN: usize = 14;
+const EVENT_MASK: u32 = 1 << 31;
+const COMMAND_TIMEOUT_MILLIS: u64 = 1000;
+
+// Message received from the window manager
+pub(crate) struct Message {
+    pub kind: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    // Events have the highest bit of their type set
+    pub fn is_event(&self) -> bool {
+        self.kind & EVENT_MASK != 0
+    }
+}
+
+// Get the path of the IPC socket, preferring i3 over sway
+pub(crate) fn socket_path() -> Option<PathBuf> {
+    env::var_os("I3SOCK")
+        .or_else(|| env::var_os("SWAYSOCK"))
+        .map(PathBuf::from)
+}
+
+// Get the outputs of the bar's monitors, using the first available fallback if one is missing
+//
+// If the bar has no monitors, all outputs should be used.
+pub(crate) fn resolve_outputs(monitors: &[Monitor], available: &[&str]) -> Option<Vec<String>> {
+    if monitors.is_empty() {
+        return None;
+    }
+
+    let outputs = monitors
+        .iter()
+        .filter_map(|monitor| {
+            iter::once(&monitor.name)
+                .chain(&monitor.fallback_names)
+                .find(|name| available.contains(&name.as_str()))
+                .cloned()
+        })
+        .collect();
+    Some(outputs)
+}
+
+// Send a query after subscribing and after every event, yielding all replies
+//
+// When the connection breaks, the error is yielded and the connection is reestablished.
+pub(crate) fn watch(
+    path: PathBuf,
+    events: &'static [&'static str],
+    query: u32,
+) -> Box<dyn Stream<Item = Result<Message, String>, Error = ()> + Send> {
+    let connections = restart_stream(move || {
+        let query_path = path.clone();
+        subscribe(&path, events)
+            .filter(|message| message.is_event() || message.kind == SUBSCRIBE)
+            .and_then(move |_| request(&query_path, query, &[]))
+            .then(|reply| Ok(reply.map_err(|err| err.to_string())))
+    });
+
+    Box::new(connections)
+}
+
+// Send a single request and wait for its reply
+fn request(
+    path: &Path,
+    kind: u32,
+    payload: &[u8],
+) -> impl Future<Item = Message, Error = IOError> + Send {
+    let request = encode(kind, payload);
+    UnixStream::connect(path)
+        .and_then(move |socket| write_all(socket, request))
+        .and_then(|(socket, _)| read_message(socket))
+        .map(|(_, message)| message)
+}
+
+// Subscribe to events, the reply to the subscription is the first message of the stream
+fn subscribe(
+    path: &Path,
+    events: &[&str],
+) -> Box<dyn Stream<Item = Message, Error = IOError> + Send> {
+    let events = events
+        .iter()
+        .map(|event| format!("\"{}\"", event))
+        .collect::<Vec<_>>();
+    let request = encode(SUBSCRIBE, format!("[{}]", events.join(",")).as_bytes());
+
+    let messages = UnixStream::connect(path)
+        .and_then(move |socket| write_all(socket, request))
+        .map(|(socket, _)| {
+            stream::unfold(socket, |socket| {
+                let message = read_message(socket).map(|(socket, message)| (message, socket));
+                Some(message)
+            })
+        })
+        .flatten_stream();
+
+    Box::new(messages)
+}
+
+// Run a command, failing if the window manager does not reply in time
+pub(crate) fn run_command(
+    path: &Path,
+    command: &str,
+) -> impl Future<Item = (), Error = IOError> + Send {
+    request(path, RUN_COMMAND, command.as_bytes())
+        .and_then(|reply| check_command(&reply.payload))
+        .timeout(Duration::from_millis(COMMAND_TIMEOUT_MILLIS))
+        .map_err(|err| match err.into_inner() {
+            Some(err) => err,
+            None => IOError::new(ErrorKind::TimedOut, "IPC command timed out"),
+        })
+}
+
+// Replies contain the result of every command which has been run
+fn check_command(payload: &[u8]) -> Result<(), IOError> {
+    let results: Vec<CommandResult> =
+        json::from_slice(payload).map_err(|err| IOError::new(ErrorKind::InvalidData, err))?;
+    match results.into_iter().find(|result| !result.success) {
+        Some(result) => {
+            let error = result
+                .error
+                .unwrap_or_else(|| String::from("command failed"));
+            Err(IOError::new(ErrorKind::Other, error))
+        }
+        None => Ok(()),
+    }
+}
+
+#[derive(Deserialize)]
+struct CommandResult {
+    success: bool,
+    error: Option<String>,
+}
+
+fn read_message(socket: UnixStream) -> impl Future<Item = (UnixStream, Message), Error = IOError> {
+    read_exact(socket, [0; HEADER_LEN]).and_then(|(socket, header)| {
+        let (len, kind) = match decode_header(&header) {
+            Ok(header) => header,
+            Err(err) => return future::Either::A(future::err(err)),
+        };
+
+        let payload = read_exact(socket, vec![0; len])
+            .map(move |(socket, payload)| (socket, Message { kind, payload }));
+        future::Either::B(payload)
+    })
+}
+
+fn encode(kind: u32, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+// Get the length and type of a message from its header
+fn decode_header(header: &[u8; HEADER_LEN]) -> Result<(usize, u32), IOError> {
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(IOError::new(ErrorKind::InvalidData, "invalid IPC message"));
+    }
+
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+    let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+    Ok((len as usize, kind))
+}
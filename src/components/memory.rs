@@ -0,0 +1,225 @@
+use serde::de::Deserialize;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::components::template::{Template, UnitBase, UnitKind, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_PROC: &str = "/proc";
+const DEFAULT_FORMAT: &str = "{mem_used}";
+const DEFAULT_INTERVAL_MILLIS: u64 = 5000;
+const PLACEHOLDERS: &[&str] = &[
+    "mem_used",
+    "mem_free",
+    "mem_total",
+    "mem_percent",
+    "swap_used",
+    "swap_free",
+    "swap_total",
+    "swap_percent",
+];
+const UNITS: &[(&str, UnitKind)] = &[
+    ("mem_used", UnitKind::Bytes),
+    ("mem_free", UnitKind::Bytes),
+    ("mem_total", UnitKind::Bytes),
+    ("swap_used", UnitKind::Bytes),
+    ("swap_free", UnitKind::Bytes),
+    ("swap_total", UnitKind::Bytes),
+];
+
+pub struct Memory {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    state: State,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_proc")]
+    proc: PathBuf,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default)]
+    base: UnitBase,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_proc() -> PathBuf {
+    PathBuf::from(DEFAULT_PROC)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Formatted memory usage
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    mem_percent: Option<f64>,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Memory {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.state.mem_percent
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Memory {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let mut memory = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse_with_units(&extra.format, PLACEHOLDERS, UNITS)?,
+            extra,
+            state: State::default(),
+        };
+        memory.state = memory.read_state();
+
+        Ok(Component::new(memory))
+    }
+
+    fn read_state(&self) -> State {
+        match read_meminfo(&self.extra.proc.join("meminfo")) {
+            Ok(info) => State {
+                text: self.format.render(&info.values(self.extra.base)),
+                mem_percent: Some(info.mem_percent()),
+                error: None,
+            },
+            Err(err) => State {
+                text: String::new(),
+                mem_percent: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+// Memory and swap usage in bytes
+struct MemInfo {
+    mem_total: u64,
+    mem_available: u64,
+    swap_total: u64,
+    swap_free: u64,
+}
+
+impl MemInfo {
+    fn values(&self, base: UnitBase) -> Vec<(&'static str, Value)> {
+        let mem_used = self.mem_total.saturating_sub(self.mem_available);
+        let swap_used = self.swap_total.saturating_sub(self.swap_free);
+
+        vec![
+            ("mem_used", Value::Bytes(mem_used, base)),
+            ("mem_free", Value::Bytes(self.mem_available, base)),
+            ("mem_total", Value::Bytes(self.mem_total, base)),
+            ("mem_percent", Value::Number(self.mem_percent(), 0)),
+            ("swap_used", Value::Bytes(swap_used, base)),
+            ("swap_free", Value::Bytes(self.swap_free, base)),
+            ("swap_total", Value::Bytes(self.swap_total, base)),
+            (
+                "swap_percent",
+                Value::Number(percent(swap_used, self.swap_total), 0),
+            ),
+        ]
+    }
+
+    fn mem_percent(&self) -> f64 {
+        percent(
+            self.mem_total.saturating_sub(self.mem_available),
+            self.mem_total,
+        )
+    }
+}
+
+fn read_meminfo(path: &Path) -> Result<MemInfo, IOError> {
+    let meminfo = fs::read_to_string(path)?;
+
+    // All values are listed in kibibytes
+    let fields = meminfo
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?.trim_end_matches(':');
+            let value = parts.next()?.parse::<u64>().ok()?;
+            Some((name, value * 1024))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let field = |name: &str| {
+        fields.get(name).cloned().ok_or_else(|| {
+            IOError::new(
+                ErrorKind::InvalidData,
+                format!("{} missing in meminfo", name),
+            )
+        })
+    };
+
+    // Kernels before 3.14 do not report the available memory
+    let mem_available = match field("MemAvailable") {
+        Ok(available) => available,
+        Err(_) => field("MemFree")? + field("Buffers")? + field("Cached")?,
+    };
+
+    Ok(MemInfo {
+        mem_total: field("MemTotal")?,
+        mem_available,
+        swap_total: field("SwapTotal").unwrap_or(0),
+        swap_free: field("SwapFree").unwrap_or(0),
+    })
+}
+
+fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        used as f64 * 100. / total as f64
+    }
+}
@@ -6,24 +6,136 @@
 //! [`Component`] trait.
 //!
 //! [`Component`]: trait.Component.html
+//!
+//! Custom components can be added by implementing the [`ComponentTrait`] and registering a factory
+//! for them in a [`ComponentRegistry`].
+//!
+//! [`ComponentTrait`]: trait.ComponentTrait.html
+//! [`ComponentRegistry`]: struct.ComponentRegistry.html
 
+mod backlight;
+mod battery;
 mod clock;
+mod command;
+mod cpu;
+mod dbus;
+mod disk;
+mod ipc;
+mod json;
+mod memory;
+mod mpd;
+mod mpris;
+mod network;
+mod nl80211;
+mod registry;
+mod sysfs;
+mod system;
+mod tail;
+mod temperature;
+mod template;
 mod undynamic;
+mod volume;
+mod wifi;
+mod window_title;
+mod workspaces;
+
+#[cfg(all(feature = "json-fmt", not(feature = "toml-fmt")))]
+use serde_json as serde_fmt;
+#[cfg(not(any(feature = "toml-fmt", feature = "json-fmt")))]
+use serde_yaml as serde_fmt;
+#[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
+use toml as serde_fmt;
 
+use futures::sync::mpsc::{self, UnboundedSender};
+use tokio::prelude::future::{self, Future};
 use tokio::prelude::stream::{self, Stream};
+use tokio::timer::{Delay, Interval};
+use tokio_process::CommandExt;
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::error::Error as StdError;
+use std::process::{Command as ProcessCommand, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use crate::components::clock::Clock;
-use crate::components::undynamic::Undynamic;
-use crate::config::Component as ConfigComponent;
-use crate::event::Event;
+use crate::config::{Actions, Monitor, StateSettings};
+use crate::event::{ComponentPosition, Event, MouseButton, MouseButtonState};
 
+pub use crate::components::registry::{ComponentFactory, ComponentRegistry};
 pub use crate::config::{ComponentSettings, Font};
 
 static COMPONENT_INDEX: AtomicUsize = AtomicUsize::new(0);
 
-pub(crate) type ComponentStream = Box<Stream<Item = ComponentID, Error = ()> + Send>;
+const RESTART_DELAY_MILLIS: u64 = 1000;
+
+/// Stream of component updates.
+///
+/// Every time this stream yields the ID of a component, the [`update`] method of that component
+/// will be called from the bar's thread.
+///
+/// [`update`]: trait.ComponentTrait.html#method.update
+pub type ComponentStream = Box<dyn Stream<Item = ComponentID, Error = ()> + Send>;
+
+/// Stream of refresh requests.
+///
+/// This is passed to [`stream_with_refresh`] and yields an item whenever the component should be
+/// polled again right away.
+///
+/// [`stream_with_refresh`]: trait.ComponentTrait.html#method.stream_with_refresh
+pub type RefreshStream = Box<dyn Stream<Item = (), Error = ()> + Send>;
+
+// Command configured for a click, resolving with its failure once it has exited
+pub(crate) type ActionFuture = Box<dyn Future<Item = (), Error = String> + Send>;
+
+// Request an update of a component after every interval
+pub(crate) fn interval_stream(id: ComponentID, interval: u64) -> ComponentStream {
+    let dur = Duration::from_millis(interval);
+    let task = Interval::new(Instant::now() + dur, dur).map(move |_| id);
+    Box::new(task.map_err(|_| ()))
+}
+
+// Restart a stream forever once it has ended, waiting before every attempt except the first one
+pub(crate) fn restart_stream<F, S>(start: F) -> impl Stream<Item = S::Item, Error = ()> + Send
+where
+    F: Fn() -> S + Send + 'static,
+    S: Stream<Error = ()> + Send + 'static,
+{
+    let first = AtomicBool::new(true);
+    stream::repeat(())
+        .and_then(move |_| {
+            let delay = if first.swap(false, Ordering::Relaxed) {
+                Duration::from_millis(0)
+            } else {
+                Duration::from_millis(RESTART_DELAY_MILLIS)
+            };
+            Delay::new(Instant::now() + delay).map_err(|_| ())
+        })
+        .map(move |_| start())
+        .flatten()
+}
+
+// Quote a string as a single argument of an i3 or MPD command
+pub(crate) fn quote(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Extra options of a component.
+///
+/// These are all options of a component in the configuration file which are not part of the
+/// [`ComponentSettings`]. The exact type depends on the configuration format feature which has
+/// been selected, so usually these options are passed to `Deserialize::deserialize` directly.
+///
+/// [`ComponentSettings`]: struct.ComponentSettings.html
+pub type ComponentOptions = serde_fmt::Value;
+
+/// Error while creating a component.
+///
+/// This is returned by a [`ComponentFactory`] when the options of a component are not valid.
+///
+/// [`ComponentFactory`]: type.ComponentFactory.html
+pub type ComponentError = Box<dyn StdError + Send + Sync>;
 
 /// Unique component identifier.
 ///
@@ -40,26 +152,198 @@ impl Default for ComponentID {
     }
 }
 
-trait ComponentTrait: Send {
+/// State of a component.
+///
+/// The state is selected by comparing the [`value`] of a component with the thresholds of the
+/// `states` in its configuration. While a state is active, its settings override the
+/// `foreground`, `background` and `fonts` of the component.
+///
+/// [`value`]: struct.Component.html#method.value
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ComponentState {
+    Good,
+    Warning,
+    Critical,
+}
+
+/// Implementation of a component.
+///
+/// This trait is used to implement the behavior of a component. Every built-in component
+/// implements it and custom components can be added by implementing it and registering a factory
+/// in the [`ComponentRegistry`].
+///
+/// Only the [`id`], [`text`] and [`settings`] methods are required, a component which never
+/// changes does not need to implement anything else.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use bar_config::bar::Bar;
+/// use bar_config::components::{
+///     Component, ComponentID, ComponentRegistry, ComponentSettings, ComponentTrait,
+/// };
+///
+/// struct Greeter {
+///     id: ComponentID,
+///     settings: ComponentSettings,
+/// }
+///
+/// impl ComponentTrait for Greeter {
+///     fn id(&self) -> ComponentID {
+///         self.id
+///     }
+///
+///     fn text(&self) -> String {
+///         String::from("Hello, World!")
+///     }
+///
+///     fn settings(&self) -> &ComponentSettings {
+///         &self.settings
+///     }
+/// }
+///
+/// let mut registry = ComponentRegistry::default();
+/// registry.register("greeter", |settings, _extra| {
+///     Ok(Component::new(Greeter {
+///         id: ComponentID::default(),
+///         settings,
+///     }))
+/// });
+///
+/// let config_file = Cursor::new(String::from(
+///     "height: 30\n\
+///      monitors:\n\
+///       - { name: \"DVI-1\" }\n\
+///      left:\n\
+///       - { name: \"greeter\" }"
+/// ));
+///
+/// let bar = Bar::load_with_registry(config_file, registry).unwrap();
+///
+/// assert_eq!(bar.left()[0].text(), String::from("Hello, World!"));
+/// ```
+///
+/// [`ComponentRegistry`]: struct.ComponentRegistry.html
+/// [`id`]: #tymethod.id
+/// [`text`]: #tymethod.text
+/// [`settings`]: #tymethod.settings
+pub trait ComponentTrait: Send {
+    /// Unique identifier of the component.
+    ///
+    /// The ID should be created once using [`ComponentID::default`] when the component is
+    /// created and must never change afterwards.
+    ///
+    /// [`ComponentID::default`]: struct.ComponentID.html
     fn id(&self) -> ComponentID;
 
+    /// Text which should currently be displayed by the component.
     fn text(&self) -> String;
 
+    /// Settings which should be used for rendering the component.
     fn settings(&self) -> &ComponentSettings;
 
-    #[doc(hidden)]
+    /// Description of the last error of the component.
+    ///
+    /// Components which depend on external resources can use this to report failures, while
+    /// still displaying their last known text. By default no error is reported.
+    fn error(&self) -> Option<String> {
+        None
+    }
+
+    /// Text which should be displayed while hovering over the component.
+    ///
+    /// Components can use this to show details which do not fit into the bar, like a calendar
+    /// for a clock. The `tooltip` option in the configuration file takes precedence over this. By
+    /// default the component has no tooltip.
+    fn tooltip(&self) -> Option<String> {
+        None
+    }
+
+    /// Numeric value used for selecting the state of the component.
+    ///
+    /// Components which display a single measurement, like a battery's capacity, should return it
+    /// here, so it can be compared with the thresholds of the configured states. By default the
+    /// component has no value and never changes its state.
+    fn value(&self) -> Option<f64> {
+        None
+    }
+
+    /// Stream of update requests.
+    ///
+    /// This stream is polled on the bar's event loop once the bar has been started. Every item
+    /// yielded by it will result in the [`update`] method being called. By default no updates are
+    /// ever requested.
+    ///
+    /// [`update`]: #method.update
     fn stream(&self) -> ComponentStream {
         Box::new(stream::empty())
     }
 
-    #[doc(hidden)]
+    /// Stream of update requests which also reacts to refresh requests.
+    ///
+    /// The `refresh` stream yields an item whenever the component should be polled again right
+    /// away, like after a command configured with `refresh_on_action` has exited. By default the
+    /// requests are merged into the [`stream`], so [`update`] is called for every one of them.
+    ///
+    /// Components which poll their resources inside the [`stream`] instead of in [`update`]
+    /// should override this to poll them again for every refresh request.
+    ///
+    /// [`stream`]: #method.stream
+    /// [`update`]: #method.update
+    fn stream_with_refresh(&self, refresh: RefreshStream) -> ComponentStream {
+        let id = self.id();
+        Box::new(self.stream().select(refresh.map(move |_| id)))
+    }
+
+    /// Update the component state.
+    ///
+    /// This is called whenever the [`stream`] of the component yielded its ID. If the component
+    /// needs to be redrawn, this should return `true`.
+    ///
+    /// [`stream`]: #method.stream
     fn update(&mut self) -> bool {
         false
     }
 
+    /// Handle a frontend event.
+    ///
+    /// This receives the events passed to [`Bar::notify`] which affect this component. Clicks and
+    /// mouse motion are only received while the pointer is inside the component. If the component
+    /// needs to be redrawn as a result of the event, this should return `true` and [`update`]
+    /// will be called right afterwards.
+    ///
+    /// [`Bar::notify`]: ../bar/struct.Bar.html#method.notify
+    /// [`update`]: #method.update
     fn notify(&mut self, _event: Event) -> bool {
         false
     }
+
+    /// Handle a frontend event with the position of the component.
+    ///
+    /// The bar calls this instead of [`notify`] for the events it routes to the component,
+    /// passing the position from the last [`PositionChange`] event of the component. Components
+    /// which locate the pointer inside of themselves can use this instead of tracking their own
+    /// position. By default the position is ignored and [`notify`] is called.
+    ///
+    /// [`notify`]: #method.notify
+    /// [`PositionChange`]: ../event/enum.Event.html#variant.PositionChange
+    fn notify_at(&mut self, event: Event, _position: ComponentPosition) -> bool {
+        self.notify(event)
+    }
+
+    /// Update the monitors of the bar.
+    ///
+    /// This is called with the [`General::monitors`] when the component is created and whenever
+    /// they are changed by a configuration reload. Components which only display information
+    /// about specific outputs can use this to filter their content. If the component needs to be
+    /// redrawn, this should return `true`.
+    ///
+    /// [`General::monitors`]: ../bar/struct.General.html#structfield.monitors
+    fn set_monitors(&mut self, _monitors: &[Monitor]) -> bool {
+        false
+    }
 }
 
 /// A single component inside the bar.
@@ -73,19 +357,50 @@ trait ComponentTrait: Send {
 /// [`text`]: #method.text
 /// [`settings`]: #method.settings
 /// [`notify`]: #method.notify
-pub struct Component(Box<ComponentTrait>);
+pub struct Component {
+    inner: Box<dyn ComponentTrait>,
+    states: Vec<StateSettings>,
+    state: Option<ComponentState>,
+    // Settings of the component merged with the settings of the active state
+    settings: Option<ComponentSettings>,
+    actions: Actions,
+    action_error: Option<String>,
+    tooltip: Option<String>,
+    // Requests the stream of the component to poll it again
+    refresh: Option<UnboundedSender<()>>,
+}
 
 impl Component {
+    /// Create a new component.
+    ///
+    /// This wraps any implementation of the [`ComponentTrait`], so it can be returned by a
+    /// [`ComponentFactory`].
+    ///
+    /// [`ComponentTrait`]: trait.ComponentTrait.html
+    /// [`ComponentFactory`]: type.ComponentFactory.html
+    pub fn new<T: ComponentTrait + 'static>(component: T) -> Self {
+        Component {
+            inner: Box::new(component),
+            states: Vec::new(),
+            state: None,
+            settings: None,
+            actions: Actions::default(),
+            action_error: None,
+            tooltip: None,
+            refresh: None,
+        }
+    }
+
     /// Return the unique identifier of this component.
     ///
-    /// Since all updates received by the [`recv`] and [`try_recv`] methods return component IDs,
-    /// this can be used to check which component needs to be updated, if a complete redraw is not
-    /// desired.
+    /// Since component updates received by the [`recv`] and [`try_recv`] methods contain component
+    /// IDs, this can be used to check which component needs to be updated, if a complete redraw is
+    /// not desired.
     ///
     /// # Examples
     ///
     /// ```
-    /// use bar_config::bar::Bar;
+    /// use bar_config::bar::{Bar, Update};
     /// use std::io::Cursor;
     ///
     /// let config_file = Cursor::new(String::from(
@@ -97,15 +412,16 @@ impl Component {
     /// ));
     ///
     /// let mut bar = Bar::load(config_file).unwrap();
-    /// let component_id = bar.recv();
     ///
-    /// println!("Component {:?} was updated!", component_id);
+    /// if let Update::Component(component_id) = bar.recv() {
+    ///     assert_eq!(component_id, bar.left()[0].id());
+    /// }
     /// ```
     ///
     /// [`recv`]: ../bar/struct.Bar.html#method.recv
     /// [`try_recv`]: ../bar/struct.Bar.html#method.try_recv
     pub fn id(&self) -> ComponentID {
-        self.0.id()
+        self.inner.id()
     }
 
     /// Get component text.
@@ -138,7 +454,7 @@ impl Component {
     ///
     /// [`settings`]: #method.settings
     pub fn text(&self) -> String {
-        self.0.text()
+        self.inner.text()
     }
 
     /// Get component settings.
@@ -169,7 +485,140 @@ impl Component {
     ///
     /// [`ComponentSettings`]: struct.ComponentSettings.html
     pub fn settings(&self) -> &ComponentSettings {
-        self.0.settings()
+        match self.settings {
+            Some(ref settings) => settings,
+            None => self.inner.settings(),
+        }
+    }
+
+    /// Get the component tooltip.
+    ///
+    /// This returns the text which should be displayed in a popup while hovering over the
+    /// component. The `tooltip` option in the configuration file is used if it is present,
+    /// otherwise the component may generate its own tooltip. If there is no tooltip, `None` is
+    /// returned.
+    ///
+    /// The [`Bar`] signals when the tooltip should be shown or hidden with the
+    /// [`Update::ShowTooltip`] and [`Update::HideTooltip`] updates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::Bar;
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }\n\
+    ///      left:\n\
+    ///       - { text: \"hello\", tooltip: \"world\" }"
+    /// ));
+    ///
+    /// let bar = Bar::load(config_file).unwrap();
+    ///
+    /// assert_eq!(bar.left()[0].tooltip(), Some(String::from("world")));
+    /// ```
+    ///
+    /// [`Bar`]: ../bar/struct.Bar.html
+    /// [`Update::ShowTooltip`]: ../bar/enum.Update.html#variant.ShowTooltip
+    /// [`Update::HideTooltip`]: ../bar/enum.Update.html#variant.HideTooltip
+    pub fn tooltip(&self) -> Option<String> {
+        self.tooltip.clone().or_else(|| self.inner.tooltip())
+    }
+
+    /// Get the numeric value of the component.
+    ///
+    /// This is the value which is compared with the thresholds of the configured `states`. If the
+    /// component does not display a single measurement, `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::Bar;
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }\n\
+    ///      left:\n\
+    ///       - { text: \"hello\" }"
+    /// ));
+    ///
+    /// let bar = Bar::load(config_file).unwrap();
+    ///
+    /// assert_eq!(bar.left()[0].value(), None);
+    /// ```
+    pub fn value(&self) -> Option<f64> {
+        self.inner.value()
+    }
+
+    /// Get the active state of the component.
+    ///
+    /// The state with the highest severity whose thresholds match the [`value`] of the component
+    /// is active. If the component has no value or no state matches, `None` is returned.
+    ///
+    /// The [`settings`] of the component already include the overrides of the active state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::Bar;
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }\n\
+    ///      left:\n\
+    ///       - text: \"hello\"\n\
+    ///         states: { warning: { below: 20, foreground: \"#ffff00\" } }"
+    /// ));
+    ///
+    /// let bar = Bar::load(config_file).unwrap();
+    ///
+    /// assert_eq!(bar.left()[0].state(), None);
+    /// ```
+    ///
+    /// [`value`]: #method.value
+    /// [`settings`]: #method.settings
+    pub fn state(&self) -> Option<ComponentState> {
+        self.state
+    }
+
+    /// Get the last component error.
+    ///
+    /// Components which rely on external resources, like running a command, will report their
+    /// failures here. The [`text`] of the component will still be available, so it is up to the
+    /// frontend how errors should be presented.
+    ///
+    /// Failures of the commands configured with the `on_click_*` and `on_scroll_*` options take
+    /// precedence over errors of the component itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::bar::Bar;
+    /// use std::io::Cursor;
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }\n\
+    ///      left:\n\
+    ///       - { name: \"command\", exec: \"exit 1\" }"
+    /// ));
+    ///
+    /// let mut bar = Bar::load(config_file).unwrap();
+    /// let _ = bar.recv();
+    ///
+    /// assert!(bar.left()[0].error().is_some());
+    /// ```
+    ///
+    /// [`text`]: #method.text
+    pub fn error(&self) -> Option<String> {
+        self.action_error.clone().or_else(|| self.inner.error())
     }
 
     /// Notify all components about a frontend event.
@@ -182,6 +631,9 @@ impl Component {
     /// To ensure that all components work properly, it is required that all events available in
     /// the [`Event`] enum are propagated properly.
     ///
+    /// The commands configured with the `on_click_*` and `on_scroll_*` options are not run by
+    /// this method, clicks passed to [`Bar::notify`] run them in the background instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -200,24 +652,116 @@ impl Component {
     /// ```
     ///
     /// [`Event`]: ../event/enum.Event.html
+    /// [`Bar::notify`]: ../bar/struct.Bar.html#method.notify
     pub fn notify(&mut self, event: Event) -> bool {
-        self.0.notify(event)
+        let dirty = self.inner.notify(event);
+        self.update_state() || dirty
     }
 
-    pub(crate) fn stream(&self) -> ComponentStream {
-        self.0.stream()
+    pub(crate) fn notify_at(&mut self, event: Event, position: ComponentPosition) -> bool {
+        let dirty = self.inner.notify_at(event, position);
+        self.update_state() || dirty
+    }
+
+    pub(crate) fn stream(&mut self) -> ComponentStream {
+        let (refresh_tx, refresh_rx) = mpsc::unbounded();
+        self.refresh = Some(refresh_tx);
+        self.inner.stream_with_refresh(Box::new(refresh_rx))
     }
 
     pub(crate) fn update(&mut self) -> bool {
-        self.0.update()
+        let dirty = self.inner.update();
+        self.update_state() || dirty
     }
-}
 
-impl From<ConfigComponent> for Component {
-    fn from(comp: ConfigComponent) -> Self {
-        match comp.name.as_str() {
-            "clock" => Clock::create(comp.settings, comp.extra),
-            _ => Undynamic::create(comp.settings, comp.extra),
+    pub(crate) fn set_monitors(&mut self, monitors: &[Monitor]) -> bool {
+        let dirty = self.inner.set_monitors(monitors);
+        self.update_state() || dirty
+    }
+
+    pub(crate) fn set_tooltip(&mut self, tooltip: Option<String>) {
+        self.tooltip = tooltip;
+    }
+
+    pub(crate) fn set_actions(&mut self, actions: Actions) {
+        self.actions = actions;
+    }
+
+    pub(crate) fn set_states(&mut self, states: Vec<StateSettings>) {
+        self.states = states;
+        self.update_state();
+    }
+
+    // Command configured for an event, which has to be run on the event loop
+    pub(crate) fn action(&self, event: Event) -> Option<ActionFuture> {
+        let (button, button_state) = match event {
+            Event::Click(button, button_state, _) => (button, button_state),
+            _ => return None,
+        };
+
+        // Buttons act once released, while every step of the wheel is a press
+        let wheel = button == MouseButton::WheelUp || button == MouseButton::WheelDown;
+        if wheel != (button_state == MouseButtonState::Pressed) {
+            return None;
         }
+
+        self.actions.command(button).map(run_command)
     }
+
+    // Store the result of an action, returning `true` if the component is dirty
+    pub(crate) fn finish_action(&mut self, result: Result<(), String>) -> bool {
+        if self.actions.refresh_on_action {
+            if let Some(ref refresh) = self.refresh {
+                let _ = refresh.unbounded_send(());
+            }
+        }
+
+        let action_error = result.err();
+        let changed = action_error != self.action_error;
+        self.action_error = action_error;
+        changed
+    }
+
+    // Select the state matching the current value, returning `true` if it has changed
+    fn update_state(&mut self) -> bool {
+        let value = self.inner.value();
+        let active = value.and_then(|value| self.states.iter().find(|s| s.matches(value)));
+        let state = active.map(|active| active.state);
+        if state == self.state {
+            return false;
+        }
+
+        self.settings = active.map(|active| active.apply(self.inner.settings()));
+        self.state = state;
+        true
+    }
+}
+
+// Run a shell command, failing if it does not exit successfully
+fn run_command(command: &str) -> ActionFuture {
+    let command = command.to_owned();
+
+    // Spawn the process lazily, so it is bound to the event loop which awaits it
+    let status = future::lazy(move || {
+        let status = ProcessCommand::new(command::default_shell())
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status_async();
+
+        future::result(status)
+            .flatten()
+            .map_err(|err| err.to_string())
+            .and_then(move |status| {
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("`{}` exited with {}", command, status))
+                }
+            })
+    });
+
+    Box::new(status)
 }
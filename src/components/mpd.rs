@@ -0,0 +1,502 @@
+use futures::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use serde::de::Deserialize;
+use tokio::io::{lines, write_all, AsyncRead, AsyncWrite, Lines, WriteHalf};
+use tokio::net::{TcpStream, UnixStream};
+use tokio::prelude::future::{loop_fn, Loop};
+use tokio::prelude::*;
+
+use std::collections::HashMap;
+use std::io::{BufReader, Error as IOError, ErrorKind};
+use std::net::ToSocketAddrs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::components::template::{DurationStyle, Template, UnitKind, Value};
+use crate::components::{
+    interval_stream, quote, restart_stream, Component, ComponentError, ComponentID,
+    ComponentOptions, ComponentSettings, ComponentStream, ComponentTrait,
+};
+use crate::event::{Event, MouseButton, MouseButtonState};
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: u16 = 6600;
+const DEFAULT_FORMAT: &str = "{artist} - {title}";
+const DEFAULT_VOLUME_STEP: i64 = 5;
+const COMMAND_TIMEOUT_MILLIS: u64 = 1000;
+const PLACEHOLDERS: &[&str] = &[
+    "artist", "album", "title", "file", "elapsed", "duration", "state", "volume",
+];
+const UNITS: &[(&str, UnitKind)] = &[
+    ("elapsed", UnitKind::Duration),
+    ("duration", UnitKind::Duration),
+];
+
+// Interval for refreshing the elapsed time while playing
+const ELAPSED_INTERVAL_MILLIS: u64 = 1000;
+
+pub struct Mpd {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    format_stopped: Template,
+    state: State,
+    shared_state: Arc<Mutex<Snapshot>>,
+    // Commands of clicks, which are sent to MPD by the stream
+    commands: UnboundedSender<String>,
+    commands_rx: Mutex<Option<UnboundedReceiver<String>>>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Extra {
+    #[serde(default = "default_host")]
+    host: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    password: Option<String>,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default)]
+    format_stopped: String,
+    #[serde(default = "default_volume_step")]
+    volume_step: i64,
+}
+
+fn default_host() -> String {
+    String::from(DEFAULT_HOST)
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_volume_step() -> i64 {
+    DEFAULT_VOLUME_STEP
+}
+
+// Playback status and current song reported by MPD
+#[derive(Clone, PartialEq)]
+struct Status {
+    status: HashMap<String, String>,
+    song: HashMap<String, String>,
+    received: Instant,
+}
+
+// Latest status received from MPD
+#[derive(Clone, Default, PartialEq)]
+struct Snapshot {
+    status: Option<Status>,
+    error: Option<String>,
+    command_error: Option<String>,
+}
+
+// Formatted state of the player
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Mpd {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let extra = self.extra.clone();
+        let shared_state = self.shared_state.clone();
+        let command_extra = self.extra.clone();
+        let command_state = self.shared_state.clone();
+        let elapsed_state = self.shared_state.clone();
+
+        // Reconnect forever, keeping the last status while MPD is unavailable
+        let connections = restart_stream(move || {
+            statuses(&extra).then(|status| Ok(status.map_err(|e| e.to_string())))
+        });
+
+        let changes = connections.filter_map(move |status| {
+            let mut snapshot = shared_state.lock().unwrap();
+
+            // Keep the last known status when the connection breaks
+            let new_snapshot = match status {
+                Ok(status) => Snapshot {
+                    status: Some(status),
+                    error: None,
+                    command_error: snapshot.command_error.clone(),
+                },
+                Err(error) => Snapshot {
+                    status: snapshot.status.clone(),
+                    error: Some(error),
+                    command_error: snapshot.command_error.clone(),
+                },
+            };
+
+            if new_snapshot != *snapshot {
+                *snapshot = new_snapshot;
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        // Commands are sent one after another, each on a new connection
+        let commands: Box<dyn Stream<Item = String, Error = ()> + Send> =
+            match self.commands_rx.lock().unwrap().take() {
+                Some(commands_rx) => Box::new(commands_rx),
+                None => Box::new(stream::empty()),
+            };
+        let command_results = commands
+            .and_then(move |command| {
+                send(&command_extra, command).then(|result| Ok(result.err().map(|e| e.to_string())))
+            })
+            .filter_map(move |command_error| {
+                let mut snapshot = command_state.lock().unwrap();
+                if command_error != snapshot.command_error {
+                    snapshot.command_error = command_error;
+                    Some(id)
+                } else {
+                    None
+                }
+            });
+
+        // MPD does not notify about the elapsed time, so it is advanced locally while playing
+        let elapsed = interval_stream(id, ELAPSED_INTERVAL_MILLIS).filter(move |_| {
+            let snapshot = elapsed_state.lock().unwrap();
+            snapshot.status.as_ref().map_or(false, |status| {
+                status.status.get("state").map(String::as_str) == Some("play")
+            })
+        });
+
+        Box::new(changes.select(command_results).select(elapsed))
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn notify(&mut self, event: Event) -> bool {
+        match event {
+            Event::Click(button, button_state, _) => {
+                // Buttons act once released, while every step of the wheel is a press
+                let wheel = button == MouseButton::WheelUp || button == MouseButton::WheelDown;
+                if wheel != (button_state == MouseButtonState::Pressed) {
+                    return false;
+                }
+
+                let command = match self.click_command(button) {
+                    Some(command) => command,
+                    None => return false,
+                };
+
+                // Sending is left to the stream, so the bar's thread never waits for MPD
+                let _ = self.commands.unbounded_send(command);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Mpd {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let (commands, commands_rx) = mpsc::unbounded();
+
+        // The player state is only available once the connection has been established
+        Ok(Component::new(Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse_with_units(&extra.format, PLACEHOLDERS, UNITS)?,
+            format_stopped: Template::parse_with_units(&extra.format_stopped, PLACEHOLDERS, UNITS)?,
+            extra,
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(Snapshot::default())),
+            commands,
+            commands_rx: Mutex::new(Some(commands_rx)),
+        }))
+    }
+
+    fn read_state(&self) -> State {
+        let snapshot = self.shared_state.lock().unwrap().clone();
+
+        // Failed commands are reported until the next one succeeds
+        let error = snapshot.command_error.or(snapshot.error);
+        let status = match snapshot.status {
+            Some(status) => status,
+            None => {
+                return State {
+                    text: String::new(),
+                    error,
+                }
+            }
+        };
+
+        let state = status.status.get("state").map(String::as_str);
+        let (state, format) = match state {
+            Some("play") => ("playing", &self.format),
+            Some("pause") => ("paused", &self.format),
+            _ => ("stopped", &self.format_stopped),
+        };
+
+        let seconds = |key: &str| status.status.get(key).and_then(|s| s.parse::<f64>().ok());
+        let mut elapsed = seconds("elapsed").unwrap_or(0.);
+        if state == "playing" {
+            elapsed += status.received.elapsed().as_secs_f64();
+        }
+        let duration = seconds("duration").unwrap_or(0.);
+        if duration > 0. {
+            elapsed = elapsed.min(duration);
+        }
+
+        let duration_value = |seconds: f64| {
+            let duration = Duration::from_secs_f64(seconds.max(0.));
+            Value::Duration(duration, DurationStyle::Minutes)
+        };
+        let mut values = vec![
+            ("elapsed", duration_value(elapsed)),
+            ("duration", duration_value(duration)),
+            ("state", state.into()),
+        ];
+
+        // Songs without tags are shown by their file name
+        let song = &status.song;
+        let file = song.get("file");
+        let title = song.get("Title").map(String::as_str).or_else(|| {
+            let file = file?;
+            file.rsplit('/').next()
+        });
+        let tags = [
+            ("artist", song.get("Artist").map(String::as_str)),
+            ("album", song.get("Album").map(String::as_str)),
+            ("title", title),
+            ("file", file.map(String::as_str)),
+        ];
+        for (name, tag) in tags.iter() {
+            if let Some(tag) = tag {
+                values.push((name, (*tag).into()));
+            }
+        }
+
+        // The volume is unknown if MPD has no mixer
+        let volume = status
+            .status
+            .get("volume")
+            .and_then(|v| v.parse::<i64>().ok());
+        if let Some(volume) = volume.filter(|volume| *volume >= 0) {
+            values.push(("volume", volume.into()));
+        }
+
+        State {
+            text: format.render(&values),
+            error,
+        }
+    }
+
+    // Map a mouse button to the MPD command it should run
+    fn click_command(&self, button: MouseButton) -> Option<String> {
+        let snapshot = self.shared_state.lock().unwrap();
+        let status = &snapshot.status.as_ref()?.status;
+
+        let volume = || status.get("volume").and_then(|v| v.parse::<i64>().ok());
+        match button {
+            MouseButton::Left if status.get("state").map(String::as_str) == Some("play") => {
+                Some(String::from("pause 1"))
+            }
+            MouseButton::Left => Some(String::from("play")),
+            MouseButton::Right => Some(String::from("next")),
+            MouseButton::WheelUp => {
+                let volume = (volume()? + self.extra.volume_step).clamp(0, 100);
+                Some(format!("setvol {}", volume))
+            }
+            MouseButton::WheelDown => {
+                let volume = (volume()? - self.extra.volume_step).clamp(0, 100);
+                Some(format!("setvol {}", volume))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Socket connected to MPD over TCP or a unix socket
+trait Socket: AsyncRead + AsyncWrite + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Send> Socket for S {}
+
+// Connect to MPD using a unix socket if the host is an absolute path
+fn connect(extra: &Extra) -> Box<dyn Future<Item = Box<dyn Socket>, Error = IOError> + Send> {
+    if extra.host.starts_with('/') {
+        let connection = UnixStream::connect(&extra.host);
+        return Box::new(connection.map(|s| Box::new(s) as Box<dyn Socket>));
+    }
+
+    let address = match (extra.host.as_str(), extra.port).to_socket_addrs() {
+        Ok(mut addresses) => addresses.next(),
+        Err(err) => return Box::new(future::err(err)),
+    };
+    match address {
+        Some(address) => {
+            let connection = TcpStream::connect(&address);
+            Box::new(connection.map(|s| Box::new(s) as Box<dyn Socket>))
+        }
+        None => {
+            let err = IOError::new(ErrorKind::NotFound, "MPD host could not be resolved");
+            Box::new(future::err(err))
+        }
+    }
+}
+
+// Stream the status of MPD, until the connection breaks
+fn statuses(extra: &Extra) -> impl Stream<Item = Status, Error = IOError> {
+    let password = extra.password.clone();
+    connect(extra)
+        .map(|socket| watch(socket, password))
+        .flatten_stream()
+}
+
+// Send a single command on a new connection
+fn send(extra: &Extra, command: String) -> impl Future<Item = (), Error = IOError> {
+    let password = extra.password.clone();
+    connect(extra)
+        .and_then(|socket| handshake(socket, password))
+        .and_then(|connection| request(connection, command))
+        .map(|_| ())
+        .timeout(Duration::from_millis(COMMAND_TIMEOUT_MILLIS))
+        .map_err(|err| match err.into_inner() {
+            Some(err) => err,
+            None => IOError::new(ErrorKind::TimedOut, "MPD command timed out"),
+        })
+}
+
+// Connection to MPD with separate halves for reading and writing
+struct Connection<S: AsyncRead + AsyncWrite> {
+    reader: Lines<BufReader<tokio::io::ReadHalf<S>>>,
+    writer: WriteHalf<S>,
+}
+
+// Wait for the greeting of MPD and authenticate if a password is set
+fn handshake<S>(
+    socket: S,
+    password: Option<String>,
+) -> impl Future<Item = Connection<S>, Error = IOError>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = socket.split();
+    let reader = lines(BufReader::new(reader));
+
+    // MPD greets every client before accepting any commands
+    let greeting = reader
+        .into_future()
+        .map_err(|(err, _)| err)
+        .and_then(move |(line, reader)| match line {
+            Some(ref line) if line.starts_with("OK MPD") => Ok(Connection { reader, writer }),
+            _ => Err(IOError::new(ErrorKind::InvalidData, "invalid MPD greeting")),
+        });
+
+    greeting.and_then(move |connection| match password {
+        Some(password) => {
+            let command = format!("password {}", quote(&password));
+            future::Either::A(request(connection, command).map(|(connection, _)| connection))
+        }
+        None => future::Either::B(future::ok(connection)),
+    })
+}
+
+// Stream the status once connected and after every change of the player or mixer
+fn watch<S>(socket: S, password: Option<String>) -> impl Stream<Item = Status, Error = IOError>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    handshake(socket, password)
+        .map(|connection| {
+            stream::unfold((connection, true), |(connection, first)| {
+                // Wait for the next change, except for the initial status
+                let idle: Box<dyn Future<Item = _, Error = IOError> + Send> = if first {
+                    Box::new(future::ok(connection))
+                } else {
+                    let idle = request(connection, String::from("idle player mixer"));
+                    Box::new(idle.map(|(connection, _)| connection))
+                };
+
+                let status = idle
+                    .and_then(|connection| request(connection, String::from("status")))
+                    .and_then(|(connection, status)| {
+                        request(connection, String::from("currentsong"))
+                            .map(move |(connection, song)| (connection, status, song))
+                    })
+                    .map(|(connection, status, song)| {
+                        let received = Instant::now();
+                        (
+                            Status {
+                                status,
+                                song,
+                                received,
+                            },
+                            (connection, false),
+                        )
+                    });
+                Some(status)
+            })
+        })
+        .flatten_stream()
+}
+
+// Send a command and collect the key-value pairs of its response
+fn request<S>(
+    connection: Connection<S>,
+    command: String,
+) -> impl Future<Item = (Connection<S>, HashMap<String, String>), Error = IOError>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let Connection { reader, writer } = connection;
+    write_all(writer, command + "\n").and_then(move |(writer, _)| {
+        loop_fn((reader, HashMap::new()), |(reader, mut pairs)| {
+            reader.into_future().map_err(|(err, _)| err).and_then(
+                move |(line, reader)| match line {
+                    Some(ref line) if line == "OK" => Ok(Loop::Break((reader, pairs))),
+                    Some(ref line) if line.starts_with("ACK") => {
+                        Err(IOError::new(ErrorKind::Other, line.clone()))
+                    }
+                    Some(line) => {
+                        if let Some((key, value)) = line.split_once(": ") {
+                            pairs.insert(key.to_owned(), value.to_owned());
+                        }
+                        Ok(Loop::Continue((reader, pairs)))
+                    }
+                    None => Err(IOError::new(
+                        ErrorKind::UnexpectedEof,
+                        "MPD closed connection",
+                    )),
+                },
+            )
+        })
+        .map(move |(reader, pairs)| (Connection { reader, writer }, pairs))
+    })
+}
@@ -0,0 +1,496 @@
+use serde::de::Deserialize;
+use tokio::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::components::dbus::{self, Connection, Message, Value};
+use crate::components::template::Template;
+use crate::components::{
+    restart_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+use crate::event::{Event, MouseButton, MouseButtonState};
+
+const DEFAULT_FORMAT: &str = "{artist} - {title}";
+
+const NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const PLACEHOLDERS: &[&str] = &["artist", "album", "title", "status", "player"];
+
+pub struct Mpris {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    format_stopped: Template,
+    state: State,
+    shared_state: Arc<Mutex<Snapshot>>,
+    connection: Arc<Mutex<Option<Connection>>>,
+    // Serials of the method calls sent for clicks, whose replies are checked by the stream
+    calls: Arc<Mutex<Vec<u32>>>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    address: Option<String>,
+    player: Option<String>,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default)]
+    format_stopped: String,
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+// Media player with its bus name and latest properties
+#[derive(Clone, PartialEq)]
+struct Player {
+    name: String,
+    owner: Option<String>,
+    status: String,
+    metadata: Vec<(String, Value)>,
+}
+
+// Latest players found on the bus
+#[derive(Clone, Default, PartialEq)]
+struct Snapshot {
+    players: Vec<Player>,
+    error: Option<String>,
+    command_error: Option<String>,
+}
+
+// Formatted state of the selected player
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Mpris {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let address = match self.extra.address.clone().or_else(dbus::session_address) {
+            Some(address) => address,
+            None => return Box::new(stream::empty()),
+        };
+        let shared_state = self.shared_state.clone();
+        let shared_connection = self.connection.clone();
+        let calls = self.calls.clone();
+
+        // Reconnect forever, keeping the last players while the bus is unavailable
+        let connections = restart_stream(move || {
+            let shared_connection = shared_connection.clone();
+            let calls = calls.clone();
+            dbus::connect(&address)
+                .map(move |(connection, messages)| {
+                    *shared_connection.lock().unwrap() = Some(connection.clone());
+                    let mut tracker = Tracker::new(&connection, calls);
+                    messages.filter_map(move |message| tracker.handle(&connection, message))
+                })
+                .flatten_stream()
+                .then(|players| Ok(players.map_err(|e| e.to_string())))
+        });
+
+        let changes = connections.filter_map(move |players| {
+            let mut snapshot = shared_state.lock().unwrap();
+
+            // Keep the last known players when the connection breaks
+            let new_snapshot = match players {
+                Ok((players, command_error)) => Snapshot {
+                    players,
+                    error: None,
+                    command_error,
+                },
+                Err(error) => Snapshot {
+                    players: snapshot.players.clone(),
+                    error: Some(error),
+                    command_error: snapshot.command_error.clone(),
+                },
+            };
+
+            if new_snapshot != *snapshot {
+                *snapshot = new_snapshot;
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        Box::new(changes)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn notify(&mut self, event: Event) -> bool {
+        match event {
+            Event::Click(button, MouseButtonState::Released, _) => {
+                let method = match button {
+                    MouseButton::Left => "PlayPause",
+                    MouseButton::Right => "Next",
+                    MouseButton::Center => "Previous",
+                    _ => return false,
+                };
+
+                // The player notifies about the result with a change of its properties
+                let snapshot = self.shared_state.lock().unwrap();
+                let connection = self.connection.lock().unwrap();
+                if let (Some(player), Some(connection)) = (self.select(&snapshot), &*connection) {
+                    let serial =
+                        connection.call(&player.name, OBJECT_PATH, PLAYER_INTERFACE, method, &[]);
+                    self.calls.lock().unwrap().push(serial);
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Mpris {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+
+        // Players are only available once the connection has been established
+        let mut snapshot = Snapshot::default();
+        if extra.address.is_none() && dbus::session_address().is_none() {
+            snapshot.error = Some(String::from("no D-Bus session bus found"));
+        }
+
+        let mut mpris = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse(&extra.format, PLACEHOLDERS)?,
+            format_stopped: Template::parse(&extra.format_stopped, PLACEHOLDERS)?,
+            extra,
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(snapshot)),
+            connection: Arc::new(Mutex::new(None)),
+            calls: Arc::new(Mutex::new(Vec::new())),
+        };
+        mpris.state = mpris.read_state();
+
+        Ok(Component::new(mpris))
+    }
+
+    fn read_state(&self) -> State {
+        let snapshot = self.shared_state.lock().unwrap();
+        let player = match self.select(&snapshot) {
+            Some(player) => player,
+            None => {
+                return State {
+                    text: String::new(),
+                    error: snapshot
+                        .command_error
+                        .clone()
+                        .or_else(|| snapshot.error.clone()),
+                }
+            }
+        };
+
+        let (status, format) = match player.status.as_str() {
+            "Playing" => ("playing", &self.format),
+            "Paused" => ("paused", &self.format),
+            _ => ("stopped", &self.format_stopped),
+        };
+
+        let mut values = vec![
+            ("status", status.into()),
+            ("player", player.short_name().into()),
+        ];
+        for (name, key) in [
+            ("artist", "xesam:artist"),
+            ("album", "xesam:album"),
+            ("title", "xesam:title"),
+        ]
+        .iter()
+        {
+            if let Some(metadata) = player.metadata(key) {
+                values.push((name, metadata.into()));
+            }
+        }
+
+        State {
+            text: format.render(&values),
+            error: snapshot
+                .command_error
+                .clone()
+                .or_else(|| snapshot.error.clone()),
+        }
+    }
+
+    // Get the pinned player, or the most active one if no player has been pinned
+    fn select<'a>(&self, snapshot: &'a Snapshot) -> Option<&'a Player> {
+        let players = &snapshot.players;
+        if let Some(ref pinned) = self.extra.player {
+            // Players with multiple instances append a suffix like `.instance1234` to their name
+            return players.iter().find(|player| {
+                let name = player.short_name();
+                name == pinned
+                    || name
+                        .strip_prefix(pinned.as_str())
+                        .map_or(false, |s| s.starts_with('.'))
+            });
+        }
+
+        players
+            .iter()
+            .find(|player| player.status == "Playing")
+            .or_else(|| players.iter().find(|player| player.status == "Paused"))
+            .or_else(|| players.first())
+    }
+}
+
+impl Player {
+    fn new(name: &str, owner: Option<String>) -> Self {
+        Self {
+            name: name.to_owned(),
+            owner,
+            status: String::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    // Name of the player without the MPRIS prefix
+    fn short_name(&self) -> &str {
+        self.name.strip_prefix(NAME_PREFIX).unwrap_or(&self.name)
+    }
+
+    // Get a metadata entry as text, joining lists like the artists with commas
+    fn metadata(&self, key: &str) -> Option<String> {
+        let (_, value) = self.metadata.iter().find(|(k, _)| k == key)?;
+        match value.as_array() {
+            Some(values) => {
+                let strings = values.iter().filter_map(Value::as_str).collect::<Vec<_>>();
+                Some(strings.join(", "))
+            }
+            None => value.as_str().map(str::to_owned),
+        }
+    }
+
+    fn apply(&mut self, properties: &[(Value, Value)]) {
+        for (key, value) in properties {
+            match key.as_str() {
+                Some("PlaybackStatus") => {
+                    self.status = value.as_str().unwrap_or_default().to_owned();
+                }
+                Some("Metadata") => {
+                    let entries = value.as_dict().unwrap_or_default();
+                    self.metadata = entries
+                        .iter()
+                        .filter_map(|(k, v)| Some((k.as_str()?.to_owned(), v.clone())))
+                        .collect();
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+// Follow the players on the bus and the changes of their properties
+struct Tracker {
+    players: Vec<Player>,
+    list_serial: u32,
+    pending: HashMap<u32, String>,
+    calls: Arc<Mutex<Vec<u32>>>,
+    command_error: Option<String>,
+}
+
+impl Tracker {
+    fn new(connection: &Connection, calls: Arc<Mutex<Vec<u32>>>) -> Self {
+        // Serials start over with every connection
+        calls.lock().unwrap().clear();
+
+        let call = |member, arg: &str| {
+            connection.call(
+                dbus::BUS_NAME,
+                dbus::BUS_PATH,
+                dbus::BUS_NAME,
+                member,
+                &[arg],
+            )
+        };
+
+        // Subscribe to all changes before looking for existing players
+        call(
+            "AddMatch",
+            &format!(
+                "type='signal',interface='{}',member='NameOwnerChanged',arg0namespace='{}'",
+                dbus::BUS_NAME,
+                NAME_PREFIX.trim_end_matches('.')
+            ),
+        );
+        call(
+            "AddMatch",
+            &format!(
+                "type='signal',interface='{}',member='PropertiesChanged',path='{}',arg0='{}'",
+                dbus::PROPERTIES_INTERFACE,
+                OBJECT_PATH,
+                PLAYER_INTERFACE
+            ),
+        );
+        let list_serial = connection.call(
+            dbus::BUS_NAME,
+            dbus::BUS_PATH,
+            dbus::BUS_NAME,
+            "ListNames",
+            &[],
+        );
+
+        Self {
+            players: Vec::new(),
+            list_serial,
+            pending: HashMap::new(),
+            calls,
+            command_error: None,
+        }
+    }
+
+    // Handle a message from the bus, returning all players and the result of the last click if
+    // either of them has changed
+    fn handle(
+        &mut self,
+        connection: &Connection,
+        message: Message,
+    ) -> Option<(Vec<Player>, Option<String>)> {
+        let previous = (self.players.clone(), self.command_error.clone());
+
+        // Always report the players of a new connection
+        let listed =
+            message.kind == dbus::METHOD_RETURN && message.reply_serial == Some(self.list_serial);
+
+        self.check_call(&message);
+        self.follow(connection, &message);
+
+        let current = (self.players.clone(), self.command_error.clone());
+        if listed || current != previous {
+            Some(current)
+        } else {
+            None
+        }
+    }
+
+    // Clicks succeed once the player has replied without an error
+    fn check_call(&mut self, message: &Message) {
+        let serial = match message.reply_serial {
+            Some(serial) => serial,
+            None => return,
+        };
+
+        let mut calls = self.calls.lock().unwrap();
+        if let Some(index) = calls.iter().position(|call| *call == serial) {
+            calls.remove(index);
+            self.command_error = match message.kind {
+                dbus::ERROR => Some(
+                    message
+                        .body
+                        .first()
+                        .and_then(Value::as_str)
+                        .or(message.error_name.as_deref())
+                        .unwrap_or("D-Bus method call failed")
+                        .to_owned(),
+                ),
+                _ => None,
+            };
+        }
+    }
+
+    // Update the players with the names, properties and signals received from the bus
+    fn follow(&mut self, connection: &Connection, message: &Message) -> Option<()> {
+        let arg = |index: usize| message.body.get(index).and_then(Value::as_str);
+
+        match (message.kind, message.member.as_deref()) {
+            (dbus::METHOD_RETURN, _) if message.reply_serial == Some(self.list_serial) => {
+                let names = message.body.first().and_then(Value::as_array);
+                for name in names.unwrap_or_default().iter().filter_map(Value::as_str) {
+                    if name.starts_with(NAME_PREFIX) {
+                        self.add(connection, name, None);
+                    }
+                }
+            }
+            (dbus::METHOD_RETURN, _) => {
+                let name = self.pending.remove(&message.reply_serial?)?;
+                let player = self.players.iter_mut().find(|player| player.name == name)?;
+
+                // Replies are sent by the unique name which owns the player's name
+                player.owner = message.sender.clone();
+                player.apply(
+                    message
+                        .body
+                        .first()
+                        .and_then(Value::as_dict)
+                        .unwrap_or_default(),
+                );
+            }
+            (dbus::ERROR, _) => {
+                self.pending.remove(&message.reply_serial?);
+            }
+            (dbus::SIGNAL, Some("NameOwnerChanged")) => {
+                let (name, owner) = (arg(0)?, arg(2)?);
+                if !name.starts_with(NAME_PREFIX) {
+                    return None;
+                }
+
+                // Players which have been closed lose their owner
+                self.players.retain(|player| player.name != name);
+                if !owner.is_empty() {
+                    self.add(connection, name, Some(owner.to_owned()));
+                }
+            }
+            (dbus::SIGNAL, Some("PropertiesChanged")) if arg(0) == Some(PLAYER_INTERFACE) => {
+                let properties = message.body.get(1).and_then(Value::as_dict)?;
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.owner.is_some() && player.owner == message.sender)?;
+                player.apply(properties);
+            }
+            _ => (),
+        }
+
+        Some(())
+    }
+
+    // Start following a player by requesting all of its properties
+    fn add(&mut self, connection: &Connection, name: &str, owner: Option<String>) {
+        let serial = connection.call(
+            name,
+            OBJECT_PATH,
+            dbus::PROPERTIES_INTERFACE,
+            "GetAll",
+            &[PLAYER_INTERFACE],
+        );
+        self.pending.insert(serial, name.to_owned());
+        self.players.push(Player::new(name, owner));
+    }
+}
@@ -0,0 +1,308 @@
+use serde::de::Deserialize;
+
+use std::ffi::CStr;
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::Instant;
+
+use crate::components::template::{Template, UnitBase, UnitKind, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_PROC: &str = "/proc";
+const DEFAULT_SYS: &str = "/sys";
+const DEFAULT_INTERFACE: &str = "auto";
+const DEFAULT_FORMAT: &str = "{interface} {ipv4}";
+const DEFAULT_INTERVAL_MILLIS: u64 = 2000;
+const PLACEHOLDERS: &[&str] = &[
+    "interface",
+    "state",
+    "rx",
+    "tx",
+    "rx_total",
+    "tx_total",
+    "ipv4",
+    "ipv6",
+];
+const UNITS: &[(&str, UnitKind)] = &[
+    ("rx", UnitKind::Bytes),
+    ("tx", UnitKind::Bytes),
+    ("rx_total", UnitKind::Bytes),
+    ("tx_total", UnitKind::Bytes),
+];
+
+pub struct Network {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    sample: Option<Sample>,
+    text: String,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_proc")]
+    proc: PathBuf,
+    #[serde(default = "default_sys")]
+    sys: PathBuf,
+    #[serde(default = "default_interface")]
+    interface: String,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default)]
+    base: UnitBase,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_proc() -> PathBuf {
+    PathBuf::from(DEFAULT_PROC)
+}
+
+fn default_sys() -> PathBuf {
+    PathBuf::from(DEFAULT_SYS)
+}
+
+fn default_interface() -> String {
+    String::from(DEFAULT_INTERFACE)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Transferred bytes of an interface at a point in time
+struct Sample {
+    interface: String,
+    rx: u64,
+    tx: u64,
+    time: Instant,
+}
+
+impl ComponentTrait for Network {
+    fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let (text, error) = match self.sample() {
+            Ok(text) => (text, None),
+            Err(err) => (String::new(), Some(err.to_string())),
+        };
+
+        if text != self.text || error != self.error {
+            self.text = text;
+            self.error = error;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Network {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let mut network = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse_with_units(&extra.format, PLACEHOLDERS, UNITS)?,
+            extra,
+            sample: None,
+            text: String::new(),
+            error: None,
+        };
+        network.update();
+
+        Ok(Component::new(network))
+    }
+
+    // Read the current interface state and format it
+    fn sample(&mut self) -> Result<String, IOError> {
+        let interface = if self.extra.interface == DEFAULT_INTERFACE {
+            default_route(&self.extra.proc.join("net/route"))?
+        } else {
+            self.extra.interface.clone()
+        };
+
+        let operstate_path = self
+            .extra
+            .sys
+            .join("class/net")
+            .join(&interface)
+            .join("operstate");
+        let state = fs::read_to_string(operstate_path)?.trim().to_owned();
+
+        let (rx, tx) = read_dev(&self.extra.proc.join("net/dev"), &interface)?;
+        let sample = Sample {
+            interface,
+            rx,
+            tx,
+            time: Instant::now(),
+        };
+
+        // Rates are only available once the same interface has been sampled twice
+        let (rx_rate, tx_rate) = match self.sample {
+            Some(ref previous) if previous.interface == sample.interface => {
+                let elapsed = sample.time.duration_since(previous.time).as_secs_f64();
+                let rate = |now: u64, before: u64| {
+                    if elapsed > 0. {
+                        (now.saturating_sub(before) as f64 / elapsed) as u64
+                    } else {
+                        0
+                    }
+                };
+                (rate(sample.rx, previous.rx), rate(sample.tx, previous.tx))
+            }
+            _ => (0, 0),
+        };
+
+        let (ipv4, ipv6) = addresses(&sample.interface);
+        let base = self.extra.base;
+        let mut values = vec![
+            ("interface", sample.interface.as_str().into()),
+            ("state", state.into()),
+            ("rx", Value::Bytes(rx_rate, base)),
+            ("tx", Value::Bytes(tx_rate, base)),
+            ("rx_total", Value::Bytes(sample.rx, base)),
+            ("tx_total", Value::Bytes(sample.tx, base)),
+        ];
+        if let Some(ipv4) = ipv4 {
+            values.push(("ipv4", ipv4.to_string().into()));
+        }
+        if let Some(ipv6) = ipv6 {
+            values.push(("ipv6", ipv6.to_string().into()));
+        }
+        let text = self.format.render(&values);
+        self.sample = Some(sample);
+
+        Ok(text)
+    }
+}
+
+// Find the interface of the default route with the lowest metric
+fn default_route(path: &Path) -> Result<String, IOError> {
+    let routes = fs::read_to_string(path)?;
+
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns = line.split_whitespace().collect::<Vec<_>>();
+            match columns.as_slice() {
+                [interface, "00000000", _, _, _, _, metric, "00000000", ..] => Some((
+                    metric.parse::<u64>().unwrap_or(u64::MAX),
+                    interface.to_string(),
+                )),
+                _ => None,
+            }
+        })
+        .min()
+        .map(|(_, interface)| interface)
+        .ok_or_else(|| IOError::new(ErrorKind::NotFound, "no default route"))
+}
+
+// Read the received and transmitted bytes of an interface
+fn read_dev(path: &Path, interface: &str) -> Result<(u64, u64), IOError> {
+    let dev = fs::read_to_string(path)?;
+
+    for line in dev.lines().skip(2) {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if name != interface {
+            continue;
+        }
+
+        let fields = parts
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|field| field.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>();
+        if fields.len() > 8 {
+            return Ok((fields[0], fields[8]));
+        }
+    }
+
+    let message = format!("interface {} not found", interface);
+    Err(IOError::new(ErrorKind::NotFound, message))
+}
+
+// Find the IPv4 and IPv6 address of an interface
+//
+// Link-local IPv6 addresses are only used if there is no other address.
+fn addresses(interface: &str) -> (Option<Ipv4Addr>, Option<Ipv6Addr>) {
+    let mut ipv4 = None;
+    let mut ipv6: Option<Ipv6Addr> = None;
+
+    unsafe {
+        let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+        if libc::getifaddrs(&mut addrs) != 0 {
+            return (None, None);
+        }
+
+        let mut current = addrs;
+        while !current.is_null() {
+            let ifaddr = &*current;
+            current = ifaddr.ifa_next;
+
+            let name = CStr::from_ptr(ifaddr.ifa_name);
+            if ifaddr.ifa_addr.is_null() || name.to_bytes() != interface.as_bytes() {
+                continue;
+            }
+
+            match i32::from((*ifaddr.ifa_addr).sa_family) {
+                libc::AF_INET if ipv4.is_none() => {
+                    let addr = &*(ifaddr.ifa_addr as *const libc::sockaddr_in);
+                    ipv4 = Some(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)));
+                }
+                libc::AF_INET6 => {
+                    let addr = &*(ifaddr.ifa_addr as *const libc::sockaddr_in6);
+                    let addr = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                    if ipv6.map_or(true, is_link_local) {
+                        ipv6 = Some(addr);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        libc::freeifaddrs(addrs);
+    }
+
+    (ipv4, ipv6)
+}
+
+fn is_link_local(addr: Ipv6Addr) -> bool {
+    addr.segments()[0] & 0xffc0 == 0xfe80
+}
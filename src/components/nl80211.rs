@@ -0,0 +1,208 @@
+// Minimal generic netlink client for querying wireless interfaces
+//
+// Only the requests necessary for retrieving the SSID of a connected interface are supported.
+
+use std::ffi::CString;
+use std::io::{Error as IOError, ErrorKind};
+use std::mem;
+use std::os::unix::io::RawFd;
+
+const NETLINK_GENERIC: i32 = 16;
+const GENL_ID_CTRL: u16 = 0x10;
+const NLMSG_ERROR: u16 = 2;
+const NLM_F_REQUEST: u16 = 1;
+
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const NL80211_CMD_GET_INTERFACE: u8 = 5;
+const NL80211_ATTR_IFINDEX: u16 = 3;
+const NL80211_ATTR_SSID: u16 = 52;
+
+const NLMSG_HEADER_LEN: usize = 16;
+const GENL_HEADER_LEN: usize = 4;
+const RECV_TIMEOUT_SECS: i64 = 1;
+
+// Get the SSID of the network the interface is connected to
+pub(crate) fn ssid(interface: &str) -> Result<Option<String>, IOError> {
+    let name = CString::new(interface).map_err(|err| IOError::new(ErrorKind::InvalidInput, err))?;
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(IOError::last_os_error());
+    }
+
+    let socket = Socket::new()?;
+
+    let family_name = b"nl80211\0";
+    let response = socket.request(
+        GENL_ID_CTRL,
+        CTRL_CMD_GETFAMILY,
+        1,
+        &[(CTRL_ATTR_FAMILY_NAME, &family_name[..])],
+    )?;
+    let family = match find_attribute(&response, CTRL_ATTR_FAMILY_ID) {
+        Some(id) if id.len() >= 2 => u16::from_ne_bytes([id[0], id[1]]),
+        _ => {
+            return Err(IOError::new(
+                ErrorKind::NotFound,
+                "nl80211 is not available",
+            ))
+        }
+    };
+
+    let index = index.to_ne_bytes();
+    let response = socket.request(
+        family,
+        NL80211_CMD_GET_INTERFACE,
+        0,
+        &[(NL80211_ATTR_IFINDEX, &index[..])],
+    )?;
+
+    let ssid = find_attribute(&response, NL80211_ATTR_SSID);
+    Ok(ssid.map(|ssid| String::from_utf8_lossy(ssid).into_owned()))
+}
+
+struct Socket(RawFd);
+
+impl Socket {
+    fn new() -> Result<Self, IOError> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                NETLINK_GENERIC,
+            )
+        };
+        if fd < 0 {
+            return Err(IOError::last_os_error());
+        }
+        let socket = Socket(fd);
+
+        // Never block the bar if the kernel does not respond
+        let timeout = libc::timeval {
+            tv_sec: RECV_TIMEOUT_SECS,
+            tv_usec: 0,
+        };
+        let result = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(IOError::last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    // Send a request and return the attributes of the response
+    fn request(
+        &self,
+        family: u16,
+        command: u8,
+        version: u8,
+        attributes: &[(u16, &[u8])],
+    ) -> Result<Vec<u8>, IOError> {
+        let mut payload = vec![command, version, 0, 0];
+        for (kind, data) in attributes {
+            let len = 4 + data.len();
+            payload.extend_from_slice(&(len as u16).to_ne_bytes());
+            payload.extend_from_slice(&kind.to_ne_bytes());
+            payload.extend_from_slice(data);
+            payload.resize(align(payload.len()), 0);
+        }
+
+        let mut message = Vec::with_capacity(NLMSG_HEADER_LEN + payload.len());
+        message.extend_from_slice(&((NLMSG_HEADER_LEN + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&family.to_ne_bytes());
+        message.extend_from_slice(&NLM_F_REQUEST.to_ne_bytes());
+        message.extend_from_slice(&1u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&payload);
+
+        let sent = unsafe {
+            libc::send(
+                self.0,
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(IOError::last_os_error());
+        }
+
+        let mut buffer = vec![0u8; 8192];
+        let received = unsafe {
+            libc::recv(
+                self.0,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                0,
+            )
+        };
+        if received < 0 {
+            return Err(IOError::last_os_error());
+        }
+        buffer.truncate(received as usize);
+
+        if buffer.len() < NLMSG_HEADER_LEN {
+            return Err(IOError::new(
+                ErrorKind::InvalidData,
+                "truncated netlink message",
+            ));
+        }
+
+        // Errors contain the negative errno directly after the header
+        let kind = u16::from_ne_bytes([buffer[4], buffer[5]]);
+        if kind == NLMSG_ERROR {
+            let errno = buffer
+                .get(NLMSG_HEADER_LEN..NLMSG_HEADER_LEN + 4)
+                .map(|errno| i32::from_ne_bytes([errno[0], errno[1], errno[2], errno[3]]))
+                .unwrap_or(0);
+            return Err(IOError::from_raw_os_error(-errno));
+        }
+
+        let len = u32::from_ne_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+        let end = len.min(buffer.len());
+        let start = NLMSG_HEADER_LEN + GENL_HEADER_LEN;
+        Ok(buffer.get(start..end).unwrap_or_default().to_vec())
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+// Find the payload of an attribute in a list of netlink attributes
+fn find_attribute(mut attributes: &[u8], kind: u16) -> Option<&[u8]> {
+    while attributes.len() >= 4 {
+        let len = u16::from_ne_bytes([attributes[0], attributes[1]]) as usize;
+        let attribute_kind = u16::from_ne_bytes([attributes[2], attributes[3]]);
+        if len < 4 || len > attributes.len() {
+            return None;
+        }
+
+        // The upper bits are used for flags
+        if attribute_kind & 0x3fff == kind {
+            return Some(&attributes[4..len]);
+        }
+
+        attributes = attributes.get(align(len)..)?;
+    }
+
+    None
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::components::backlight::Backlight;
+use crate::components::battery::Battery;
+use crate::components::clock::Clock;
+use crate::components::command::Command;
+use crate::components::cpu::Cpu;
+use crate::components::disk::Disk;
+use crate::components::memory::Memory;
+use crate::components::mpd::Mpd;
+use crate::components::mpris::Mpris;
+use crate::components::network::Network;
+use crate::components::system::System;
+use crate::components::tail::Tail;
+use crate::components::temperature::Temperature;
+use crate::components::undynamic::Undynamic;
+use crate::components::volume::Volume;
+use crate::components::wifi::Wifi;
+use crate::components::window_title::WindowTitle;
+use crate::components::workspaces::Workspaces;
+use crate::components::{Component, ComponentError, ComponentOptions, ComponentSettings};
+
+/// Factory for creating a component.
+///
+/// The factory receives the settings of the component with all fallbacks from the bar's defaults
+/// already applied and all extra options of the component from the configuration file. If the
+/// options are not valid, a [`ComponentError`] should be returned.
+///
+/// [`ComponentError`]: type.ComponentError.html
+pub type ComponentFactory =
+    Box<dyn Fn(ComponentSettings, ComponentOptions) -> Result<Component, ComponentError> + Send>;
+
+/// Registry of all available components.
+///
+/// The registry maps the `name` of a component in the configuration file to the factory which
+/// is used to create it. The default registry contains all built-in components, additional
+/// components can be added using the [`register`] method.
+///
+/// Components without a name or with a name that has not been registered will always be created
+/// as static text components.
+///
+/// [`register`]: #method.register
+pub struct ComponentRegistry {
+    factories: HashMap<String, ComponentFactory>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        let mut registry = ComponentRegistry {
+            factories: HashMap::new(),
+        };
+
+        registry.register("backlight", Backlight::create);
+        registry.register("battery", Battery::create);
+        registry.register("clock", Clock::create);
+        registry.register("command", Command::create);
+        registry.register("cpu", Cpu::create);
+        registry.register("disk", Disk::create);
+        registry.register("memory", Memory::create);
+        registry.register("mpd", Mpd::create);
+        registry.register("mpris", Mpris::create);
+        registry.register("network", Network::create);
+        registry.register("system", System::create);
+        registry.register("tail", Tail::create);
+        registry.register("temperature", Temperature::create);
+        registry.register("volume", Volume::create);
+        registry.register("wifi", Wifi::create);
+        registry.register("window_title", WindowTitle::create);
+        registry.register("workspaces", Workspaces::create);
+
+        registry
+    }
+}
+
+impl ComponentRegistry {
+    /// Register a new component.
+    ///
+    /// This registers the `factory` for all components with the specified `name`. If there
+    /// already is a factory for the `name`, it will be replaced, so this can also be used to
+    /// override built-in components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use bar_config::bar::Bar;
+    /// use bar_config::components::ComponentRegistry;
+    ///
+    /// // Register `time` as an alias for the built-in clock
+    /// let mut registry = ComponentRegistry::default();
+    /// registry.register("time", |settings, extra| {
+    ///     ComponentRegistry::default().create("clock", settings, extra)
+    /// });
+    ///
+    /// let config_file = Cursor::new(String::from(
+    ///     "height: 30\n\
+    ///      monitors:\n\
+    ///       - { name: \"DVI-1\" }\n\
+    ///      left:\n\
+    ///       - { name: \"time\" }"
+    /// ));
+    ///
+    /// let bar = Bar::load_with_registry(config_file, registry).unwrap();
+    ///
+    /// assert_eq!(bar.left().len(), 1);
+    /// ```
+    pub fn register<F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        F: Fn(ComponentSettings, ComponentOptions) -> Result<Component, ComponentError>
+            + Send
+            + 'static,
+    {
+        self.factories.insert(name.to_owned(), Box::new(factory));
+        self
+    }
+
+    /// Create a new component.
+    ///
+    /// This looks up the factory registered for `name` and uses it to create the component. If
+    /// there is no factory for `name`, a static text component is created instead.
+    ///
+    /// # Errors
+    ///
+    /// If the `extra` options are not valid for the component.
+    pub fn create(
+        &self,
+        name: &str,
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        match self.factories.get(name) {
+            Some(factory) => factory(settings, extra),
+            None => Undynamic::create(settings, extra),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// Read a file and remove the trailing newline
+pub(crate) fn read_string(path: &Path) -> Result<String, IOError> {
+    Ok(fs::read_to_string(path)?.trim().to_owned())
+}
+
+// Read a file containing a single integer
+pub(crate) fn read_value(path: &Path) -> Result<u64, IOError> {
+    read_string(path)?
+        .parse()
+        .map_err(|err| IOError::new(ErrorKind::InvalidData, err))
+}
+
+// Paths of all entries in a directory, sorted by name
+pub(crate) fn sorted_entries(path: &Path) -> Result<Vec<PathBuf>, IOError> {
+    let mut entries = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    Ok(entries)
+}
@@ -0,0 +1,161 @@
+use serde::de::Deserialize;
+
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::components::template::{DurationStyle, Template, UnitKind, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_PROC: &str = "/proc";
+const DEFAULT_FORMAT: &str = "{load1} {load5} {load15}";
+const DEFAULT_INTERVAL_MILLIS: u64 = 5000;
+const PLACEHOLDERS: &[&str] = &["load1", "load5", "load15", "running", "tasks", "uptime"];
+const UNITS: &[(&str, UnitKind)] = &[("uptime", UnitKind::Duration)];
+
+pub struct System {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    state: State,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_proc")]
+    proc: PathBuf,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_proc() -> PathBuf {
+    PathBuf::from(DEFAULT_PROC)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Formatted load and uptime
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+impl ComponentTrait for System {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl System {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let mut system = Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse_with_units(&extra.format, PLACEHOLDERS, UNITS)?,
+            extra,
+            state: State::default(),
+        };
+        system.state = system.read_state();
+
+        Ok(Component::new(system))
+    }
+
+    fn read_state(&self) -> State {
+        let values = read_loadavg(&self.extra.proc.join("loadavg")).and_then(|mut values| {
+            let uptime = read_uptime(&self.extra.proc.join("uptime"))?;
+            let uptime = Duration::from_secs(uptime);
+            values.push(("uptime", Value::Duration(uptime, DurationStyle::Human)));
+            Ok(values)
+        });
+
+        match values {
+            Ok(values) => State {
+                text: self.format.render(&values),
+                error: None,
+            },
+            Err(err) => State {
+                text: String::new(),
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+// Read the load averages and the number of running and total tasks
+fn read_loadavg(path: &Path) -> Result<Vec<(&'static str, Value)>, IOError> {
+    let loadavg = fs::read_to_string(path)?;
+    let fields = loadavg.split_whitespace().collect::<Vec<_>>();
+    let invalid = || IOError::new(ErrorKind::InvalidData, "invalid loadavg");
+
+    match fields.as_slice() {
+        [load1, load5, load15, tasks, ..] => {
+            let (running, total) = tasks.split_once('/').ok_or_else(invalid)?;
+            let load = |load: &str| load.parse::<f64>().map(|load| Value::Number(load, 2));
+            let count = |count: &str| count.parse::<u64>().map(Value::from);
+
+            Ok(vec![
+                ("load1", load(load1).map_err(|_| invalid())?),
+                ("load5", load(load5).map_err(|_| invalid())?),
+                ("load15", load(load15).map_err(|_| invalid())?),
+                ("running", count(running).map_err(|_| invalid())?),
+                ("tasks", count(total).map_err(|_| invalid())?),
+            ])
+        }
+        _ => Err(invalid()),
+    }
+}
+
+// Read the uptime in seconds
+fn read_uptime(path: &Path) -> Result<u64, IOError> {
+    let uptime = fs::read_to_string(path)?;
+    uptime
+        .split_whitespace()
+        .next()
+        .and_then(|uptime| uptime.parse::<f64>().ok())
+        .map(|uptime| uptime as u64)
+        .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "invalid uptime"))
+}
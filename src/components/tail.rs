@@ -0,0 +1,212 @@
+use serde::de::Deserialize;
+use tokio::io::lines;
+use tokio::prelude::*;
+use tokio::timer::Delay;
+use tokio_process::CommandExt;
+
+use std::cmp;
+use std::io::BufReader;
+use std::process::{Command as ProcessCommand, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::components::command::default_shell;
+use crate::components::{
+    Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings, ComponentStream,
+    ComponentTrait,
+};
+
+const DEFAULT_BACKOFF_MILLIS: u64 = 1000;
+const DEFAULT_MAX_BACKOFF_MILLIS: u64 = 60000;
+
+pub struct Tail {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    state: State,
+    shared_state: Arc<Mutex<State>>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Extra {
+    exec: String,
+    #[serde(default = "default_shell")]
+    shell: String,
+    #[serde(default)]
+    placeholder: String,
+    #[serde(default = "default_backoff")]
+    backoff: u64,
+    #[serde(default = "default_max_backoff")]
+    max_backoff: u64,
+}
+
+fn default_backoff() -> u64 {
+    DEFAULT_BACKOFF_MILLIS
+}
+
+fn default_max_backoff() -> u64 {
+    DEFAULT_MAX_BACKOFF_MILLIS
+}
+
+// Latest output of the process
+#[derive(Clone, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+// Events emitted by a single run of the process
+enum ProcessEvent {
+    Line(String),
+    Exit(Result<ExitStatus, String>),
+}
+
+impl ComponentTrait for Tail {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let extra = self.extra.clone();
+        let shared_state = self.shared_state.clone();
+
+        // Consecutive exits of the process without printing anything
+        let failures = Arc::new(AtomicUsize::new(0));
+
+        // Respawn the process forever, backing off when it keeps dying
+        let delay_failures = failures.clone();
+        let delay_extra = extra.clone();
+        let runs = stream::repeat(())
+            .and_then(move |_| {
+                let failures = delay_failures.load(Ordering::Relaxed);
+                let delay = backoff(&delay_extra, failures);
+                Delay::new(Instant::now() + delay).map_err(|_| ())
+            })
+            .map(move |_| spawn(&extra))
+            .flatten();
+
+        let placeholder = self.extra.placeholder.clone();
+        let task = runs.filter_map(move |event| {
+            let new_state = match event {
+                ProcessEvent::Line(text) => {
+                    failures.store(0, Ordering::Relaxed);
+                    State { text, error: None }
+                }
+                ProcessEvent::Exit(status) => {
+                    failures.fetch_add(1, Ordering::Relaxed);
+                    let error = match status {
+                        Ok(ref status) if status.success() => None,
+                        Ok(status) => match status.code() {
+                            Some(code) => Some(format!("process exited with status {}", code)),
+                            None => Some(String::from("process was terminated by a signal")),
+                        },
+                        Err(err) => Some(err),
+                    };
+                    State {
+                        text: placeholder.clone(),
+                        error,
+                    }
+                }
+            };
+
+            let mut state = shared_state.lock().unwrap();
+            if new_state != *state {
+                *state = new_state;
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        Box::new(task)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.shared_state.lock().unwrap();
+        if *state != self.state {
+            self.state = state.clone();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Tail {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+
+        // Show the placeholder until the process has printed its first line
+        let state = State {
+            text: extra.placeholder.clone(),
+            error: None,
+        };
+
+        Ok(Component::new(Self {
+            settings,
+            id: ComponentID::default(),
+            shared_state: Arc::new(Mutex::new(state.clone())),
+            state,
+            extra,
+        }))
+    }
+}
+
+// Delay before the next spawn, doubling with every consecutive failure
+fn backoff(extra: &Extra, failures: usize) -> Duration {
+    if failures == 0 {
+        return Duration::from_millis(0);
+    }
+
+    let factor = 1u64.checked_shl(failures as u32 - 1).unwrap_or(u64::MAX);
+    let delay = extra.backoff.saturating_mul(factor);
+    Duration::from_millis(cmp::min(delay, extra.max_backoff))
+}
+
+// Spawn the process and stream every line of its stdout, followed by its exit
+fn spawn(extra: &Extra) -> Box<dyn Stream<Item = ProcessEvent, Error = ()> + Send> {
+    let child = ProcessCommand::new(&extra.shell)
+        .arg("-c")
+        .arg(&extra.exec)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn_async();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            let exit = ProcessEvent::Exit(Err(err.to_string()));
+            return Box::new(stream::once(Ok(exit)));
+        }
+    };
+
+    let stdout = child.stdout().take().unwrap();
+
+    // Stop reading once stdout has errored, the exit status will still be reported
+    let lines = lines(BufReader::new(stdout))
+        .map(Some)
+        .or_else(|_| Ok(None))
+        .take_while(|line| Ok(line.is_some()))
+        .filter_map(|line| line.map(ProcessEvent::Line));
+    let exit = child.then(|status| Ok(ProcessEvent::Exit(status.map_err(|e| e.to_string()))));
+
+    Box::new(lines.chain(exit.into_stream()))
+}
@@ -0,0 +1,282 @@
+use serde::de::Deserialize;
+
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::components::sysfs::{read_string, sorted_entries};
+use crate::components::template::{Template, Value};
+use crate::components::{
+    interval_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_SYS: &str = "/sys";
+const DEFAULT_FORMAT: &str = "{temperature}{unit}";
+const DEFAULT_INTERVAL_MILLIS: u64 = 5000;
+const PLACEHOLDERS: &[&str] = &["temperature", "unit", "state"];
+
+pub struct Temperature {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    formats: Formats,
+    // Input of the sensor, once it has been found
+    path: Option<PathBuf>,
+    state: State,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_sys")]
+    sys: PathBuf,
+    hwmon: Option<String>,
+    label: Option<String>,
+    thermal_zone: Option<String>,
+    #[serde(default)]
+    unit: Unit,
+    warning: Option<f64>,
+    critical: Option<f64>,
+    #[serde(default = "default_format")]
+    format: String,
+    format_warning: Option<String>,
+    format_critical: Option<String>,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_sys() -> PathBuf {
+    PathBuf::from(DEFAULT_SYS)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Parsed formats for every state
+struct Formats {
+    normal: Template,
+    warning: Option<Template>,
+    critical: Option<Template>,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl Default for Unit {
+    fn default() -> Self {
+        Unit::Celsius
+    }
+}
+
+// Formatted temperature of the sensor
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    temperature: Option<f64>,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Temperature {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.state.temperature
+    }
+
+    fn stream(&self) -> ComponentStream {
+        interval_stream(self.id(), self.extra.interval)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Temperature {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+
+        let parse = |format: &Option<String>| {
+            format
+                .as_ref()
+                .map(|format| Template::parse(format, PLACEHOLDERS))
+                .transpose()
+        };
+        let formats = Formats {
+            normal: Template::parse(&extra.format, PLACEHOLDERS)?,
+            warning: parse(&extra.format_warning)?,
+            critical: parse(&extra.format_critical)?,
+        };
+
+        let mut temperature = Self {
+            settings,
+            id: ComponentID::default(),
+            extra,
+            formats,
+            path: None,
+            state: State::default(),
+        };
+        temperature.state = temperature.read_state();
+
+        Ok(Component::new(temperature))
+    }
+
+    fn read_state(&mut self) -> State {
+        let millidegrees = match self.find_sensor().and_then(|path| read_millidegrees(&path)) {
+            Ok(millidegrees) => millidegrees,
+            Err(err) => {
+                return State {
+                    text: String::new(),
+                    temperature: None,
+                    error: Some(err.to_string()),
+                }
+            }
+        };
+
+        let celsius = millidegrees as f64 / 1000.;
+        let (temperature, unit) = match self.extra.unit {
+            Unit::Celsius => (celsius, "°C"),
+            Unit::Fahrenheit => (celsius * 1.8 + 32., "°F"),
+        };
+
+        // Thresholds use the configured unit
+        let above = |threshold: Option<f64>| threshold.map_or(false, |t| temperature >= t);
+        let formats = &self.formats;
+        let (state, format) = if above(self.extra.critical) {
+            (
+                "critical",
+                formats.critical.as_ref().or(formats.warning.as_ref()),
+            )
+        } else if above(self.extra.warning) {
+            ("warning", formats.warning.as_ref())
+        } else {
+            ("normal", None)
+        };
+
+        let values = [
+            ("temperature", Value::Number(temperature, 0)),
+            ("unit", unit.into()),
+            ("state", state.into()),
+        ];
+
+        State {
+            text: format.unwrap_or(&formats.normal).render(&values),
+            temperature: Some(temperature),
+            error: None,
+        }
+    }
+
+    // Look up the sensor until it has been found, since the numbering is not stable across boots
+    fn find_sensor(&mut self) -> Result<PathBuf, IOError> {
+        if let Some(ref path) = self.path {
+            return Ok(path.clone());
+        }
+
+        let extra = &self.extra;
+        let path = if extra.hwmon.is_some() || extra.label.is_some() {
+            find_hwmon(&extra.sys, extra.hwmon.as_deref(), extra.label.as_deref())?
+        } else {
+            find_thermal_zone(&extra.sys, extra.thermal_zone.as_deref())?
+        };
+        self.path = Some(path.clone());
+        Ok(path)
+    }
+}
+
+// Find the input of an hwmon sensor by its device name and label
+fn find_hwmon(sys: &Path, name: Option<&str>, label: Option<&str>) -> Result<PathBuf, IOError> {
+    for device in sorted_entries(&sys.join("class/hwmon"))? {
+        if let Some(name) = name {
+            if read_string(&device.join("name")).ok().as_deref() != Some(name) {
+                continue;
+            }
+        }
+
+        // Sensors are numbered starting at 1, without any guarantee for gaps
+        let mut sensors = sorted_entries(&device)?
+            .into_iter()
+            .filter_map(|path| {
+                let file_name = path.file_name()?.to_str()?;
+                let index = file_name.strip_prefix("temp")?.strip_suffix("_input")?;
+                Some((index.parse::<u32>().ok()?, path.clone()))
+            })
+            .collect::<Vec<_>>();
+        sensors.sort();
+
+        for (index, input) in sensors {
+            let sensor_label = read_string(&device.join(format!("temp{}_label", index))).ok();
+            if label.is_none() || sensor_label.as_deref() == label {
+                return Ok(input);
+            }
+        }
+    }
+
+    let message = match (name, label) {
+        (Some(name), Some(label)) => format!("no hwmon sensor {} in {}", label, name),
+        (Some(name), None) => format!("no hwmon device {}", name),
+        (None, Some(label)) => format!("no hwmon sensor {}", label),
+        (None, None) => String::from("no hwmon sensor found"),
+    };
+    Err(IOError::new(ErrorKind::NotFound, message))
+}
+
+// Find the temperature of a thermal zone by its type, or the first zone
+fn find_thermal_zone(sys: &Path, zone_type: Option<&str>) -> Result<PathBuf, IOError> {
+    for zone in sorted_entries(&sys.join("class/thermal"))? {
+        let is_zone = zone
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| name.starts_with("thermal_zone"));
+        if !is_zone {
+            continue;
+        }
+
+        if zone_type.is_none() || read_string(&zone.join("type")).ok().as_deref() == zone_type {
+            return Ok(zone.join("temp"));
+        }
+    }
+
+    let message = match zone_type {
+        Some(zone_type) => format!("no thermal zone {}", zone_type),
+        None => String::from("no thermal zone found"),
+    };
+    Err(IOError::new(ErrorKind::NotFound, message))
+}
+
+// Read a temperature, which might be below zero
+fn read_millidegrees(path: &Path) -> Result<i64, IOError> {
+    read_string(path)?
+        .parse()
+        .map_err(|err| IOError::new(ErrorKind::InvalidData, err))
+}
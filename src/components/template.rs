@@ -0,0 +1,593 @@
+// Format strings shared by all components
+//
+// Placeholders look like `{name}` or `{name:spec}`, where the optional spec follows the syntax
+// `[[fill]align][0][width][.precision][unit]` known from Rust's format strings. Literal braces
+// are written as `{{` and `}}`.
+//
+// Units are only accepted by placeholders which are declared with the matching kind of value.
+//
+// Conditionals render their content only if a value is present with `{?name:content}`, or
+// only if it is missing with `{!name:content}`.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::time::Duration;
+
+// Value which can be inserted into a template
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Value {
+    Text(String),
+    // Number with the precision used when the placeholder does not specify one
+    Number(f64, usize),
+    Bytes(u64, UnitBase),
+    Duration(Duration, DurationStyle),
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_owned())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(number: u64) -> Self {
+        Value::Number(number as f64, 0)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(number: i64) -> Self {
+        Value::Number(number as f64, 0)
+    }
+}
+
+// Default representation of durations without a unit
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum DurationStyle {
+    // Minutes and seconds like `3:05`
+    Minutes,
+    // Hours and minutes like `1:05`
+    Hours,
+    // The two most significant units like `3d 4h`
+    Human,
+}
+
+// Base used for scaling byte sizes to human-readable units
+#[derive(Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(try_from = "u64")]
+pub(crate) enum UnitBase {
+    Binary,
+    Decimal,
+}
+
+impl Default for UnitBase {
+    fn default() -> Self {
+        UnitBase::Binary
+    }
+}
+
+impl TryFrom<u64> for UnitBase {
+    type Error = String;
+
+    fn try_from(base: u64) -> Result<Self, Self::Error> {
+        match base {
+            1024 => Ok(UnitBase::Binary),
+            1000 => Ok(UnitBase::Decimal),
+            _ => Err(format!("invalid unit base {}, expected 1000 or 1024", base)),
+        }
+    }
+}
+
+// Kind of value which a placeholder accepts units for
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum UnitKind {
+    Bytes,
+    Duration,
+}
+
+// Error for format strings which are invalid or contain unknown placeholders
+#[derive(Debug)]
+pub(crate) struct TemplateError(String);
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for TemplateError {}
+
+// Parsed format string
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Part {
+    Text(String),
+    Placeholder(String, Spec),
+    Conditional(String, bool, Template),
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    unit: Option<Unit>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Fixed unit for byte sizes and durations
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Unit {
+    Bytes(f64, &'static str),
+    Seconds(f64, &'static str),
+}
+
+const BYTE_UNITS: [(&str, f64); 9] = [
+    ("B", 1.),
+    ("KiB", 1024.),
+    ("MiB", 1_048_576.),
+    ("GiB", 1_073_741_824.),
+    ("TiB", 1_099_511_627_776.),
+    ("kB", 1e3),
+    ("MB", 1e6),
+    ("GB", 1e9),
+    ("TB", 1e12),
+];
+
+const DURATION_UNITS: [(&str, f64); 4] = [("s", 1.), ("m", 60.), ("h", 3600.), ("d", 86400.)];
+
+impl Template {
+    // Parse a format string which may only use the given placeholders
+    pub fn parse(format: &str, placeholders: &[&str]) -> Result<Self, TemplateError> {
+        Self::parse_with_units(format, placeholders, &[])
+    }
+
+    // Parse a format string whose placeholders may only use the units of their declared kinds
+    pub fn parse_with_units(
+        format: &str,
+        placeholders: &[&str],
+        units: &[(&str, UnitKind)],
+    ) -> Result<Self, TemplateError> {
+        let mut chars = format.chars().peekable();
+        let template = Parser {
+            format,
+            placeholders,
+            units,
+        }
+        .parse(&mut chars, false)?;
+        Ok(template)
+    }
+
+    // Render the template, placeholders without a value are left empty
+    pub fn render(&self, values: &[(&str, Value)]) -> String {
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(literal) => text.push_str(literal),
+                Part::Placeholder(name, spec) => {
+                    let value = values.iter().find(|(key, _)| key == name).map(|(_, v)| v);
+                    text.push_str(&spec.apply(value));
+                }
+                Part::Conditional(name, present, template) => {
+                    if values.iter().any(|(key, _)| key == name) == *present {
+                        text.push_str(&template.render(values));
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+struct Parser<'a> {
+    format: &'a str,
+    placeholders: &'a [&'a str],
+    units: &'a [(&'a str, UnitKind)],
+}
+
+impl Parser<'_> {
+    // Parse until the end of the format or the closing brace of a conditional
+    fn parse(&self, chars: &mut Peekable<Chars>, nested: bool) -> Result<Template, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+
+        loop {
+            match chars.next() {
+                Some('{') if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                Some('}') if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                Some('{') => {
+                    if !literal.is_empty() {
+                        parts.push(Part::Text(literal.split_off(0)));
+                    }
+                    parts.push(self.parse_placeholder(chars)?);
+                }
+                Some('}') if nested => break,
+                Some('}') => return Err(self.error("unmatched `}`")),
+                Some(c) => literal.push(c),
+                None if nested => return Err(self.error("unclosed conditional")),
+                None => break,
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    fn parse_placeholder(&self, chars: &mut Peekable<Chars>) -> Result<Part, TemplateError> {
+        let present = match chars.peek() {
+            Some('?') => Some(true),
+            Some('!') => Some(false),
+            _ => None,
+        };
+
+        if let Some(present) = present {
+            chars.next();
+            let name = chars.by_ref().take_while(|c| *c != ':').collect::<String>();
+            self.check_name(&name)?;
+            let template = self.parse(chars, true)?;
+            return Ok(Part::Conditional(name, present, template));
+        }
+
+        let mut placeholder = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => placeholder.push(c),
+                None => return Err(self.error("unclosed placeholder")),
+            }
+        }
+
+        let (name, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+        self.check_name(name)?;
+        let spec = parse_spec(spec)
+            .ok_or_else(|| self.error(&format!("invalid spec `{}` of `{{{}}}`", spec, name)))?;
+        self.check_unit(name, &spec)?;
+        Ok(Part::Placeholder(name.to_owned(), spec))
+    }
+
+    fn check_unit(&self, name: &str, spec: &Spec) -> Result<(), TemplateError> {
+        let (kind, unit) = match spec.unit {
+            Some(Unit::Bytes(_, unit)) => (UnitKind::Bytes, unit),
+            Some(Unit::Seconds(_, unit)) => (UnitKind::Duration, unit),
+            None => return Ok(()),
+        };
+
+        if self.units.contains(&(name, kind)) {
+            Ok(())
+        } else {
+            Err(self.error(&format!(
+                "unit `{}` is not supported by `{{{}}}`",
+                unit, name
+            )))
+        }
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), TemplateError> {
+        if self.placeholders.contains(&name) {
+            Ok(())
+        } else {
+            let expected = self.placeholders.join("}, {");
+            Err(self.error(&format!(
+                "unknown placeholder `{{{}}}`, expected one of {{{}}}",
+                name, expected
+            )))
+        }
+    }
+
+    fn error(&self, message: &str) -> TemplateError {
+        TemplateError(format!("{} in format \"{}\"", message, self.format))
+    }
+}
+
+// Parse the spec of a placeholder, returning `None` if it is invalid
+fn parse_spec(spec: &str) -> Option<Spec> {
+    let align = |c| match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    };
+
+    let mut result = Spec::default();
+    let mut chars = spec.chars().collect::<Vec<_>>();
+    match chars.as_slice() {
+        [fill, a, ..] if align(*a).is_some() => {
+            result.fill = Some(*fill);
+            result.align = align(*a);
+            chars.drain(..2);
+        }
+        [a, ..] if align(*a).is_some() => {
+            result.align = align(*a);
+            chars.drain(..1);
+        }
+        _ => (),
+    }
+
+    if chars.first() == Some(&'0') {
+        result.zero = true;
+        chars.remove(0);
+    }
+
+    let digits = |chars: &mut Vec<char>| {
+        let len = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+        let number = chars.drain(..len).collect::<String>();
+        number.parse::<usize>().ok()
+    };
+
+    result.width = digits(&mut chars).unwrap_or(0);
+    if chars.first() == Some(&'.') {
+        chars.remove(0);
+        result.precision = Some(digits(&mut chars)?);
+    }
+
+    let unit = chars.into_iter().collect::<String>();
+    if !unit.is_empty() {
+        let bytes = BYTE_UNITS.iter().find(|(name, _)| *name == unit);
+        let seconds = DURATION_UNITS.iter().find(|(name, _)| *name == unit);
+        result.unit = match (bytes, seconds) {
+            (Some((name, factor)), _) => Some(Unit::Bytes(*factor, name)),
+            (_, Some((name, factor))) => Some(Unit::Seconds(*factor, name)),
+            _ => return None,
+        };
+    }
+
+    Some(result)
+}
+
+impl Spec {
+    // Format a value and pad it to the width
+    fn apply(&self, value: Option<&Value>) -> String {
+        let text = match value {
+            Some(value) => self.format(value),
+            None => String::new(),
+        };
+
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+
+        // Numbers are aligned to the right and may be padded with zeros after their sign
+        let numeric = !matches!(value, None | Some(Value::Text(_)));
+        if self.zero && numeric && self.align.is_none() {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return format!("{}{}{}", sign, "0".repeat(self.width - len), digits);
+        }
+
+        let align = match self.align {
+            Some(align) => align,
+            None if numeric => Align::Right,
+            None => Align::Left,
+        };
+        let padding = self.width - len;
+        let (left, right) = match align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+
+        let fill = self.fill.unwrap_or(' ').to_string();
+        format!("{}{}{}", fill.repeat(left), text, fill.repeat(right))
+    }
+
+    fn format(&self, value: &Value) -> String {
+        match (value, self.unit) {
+            (Value::Text(text), _) => match self.precision {
+                Some(precision) => text.chars().take(precision).collect(),
+                None => text.clone(),
+            },
+            (Value::Number(number, precision), _) => {
+                format!("{:.*}", self.precision.unwrap_or(*precision), number)
+            }
+            (Value::Bytes(bytes, _), Some(Unit::Bytes(factor, name))) => {
+                let precision = self.precision.unwrap_or(1);
+                format!("{:.*}{}", precision, *bytes as f64 / factor, name)
+            }
+            (Value::Bytes(bytes, base), _) => format_bytes(*bytes, *base, self.precision),
+            (Value::Duration(duration, _), Some(Unit::Seconds(factor, name))) => {
+                let precision = self.precision.unwrap_or(0);
+                format!("{:.*}{}", precision, duration.as_secs_f64() / factor, name)
+            }
+            (Value::Duration(duration, style), _) => format_duration(*duration, *style),
+        }
+    }
+}
+
+// Format a number of bytes using the largest fitting unit
+fn format_bytes(bytes: u64, base: UnitBase, precision: Option<usize>) -> String {
+    let (base, units) = match base {
+        UnitBase::Binary => (1024., ["B", "KiB", "MiB", "GiB", "TiB", "PiB"]),
+        UnitBase::Decimal => (1000., ["B", "kB", "MB", "GB", "TB", "PB"]),
+    };
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit + 1 < units.len() {
+        value /= base;
+        unit += 1;
+    }
+
+    // Bytes are whole numbers, so they only get a precision when asked for
+    let precision = match precision {
+        Some(precision) => precision,
+        None if unit == 0 => 0,
+        None => 1,
+    };
+    format!("{:.*}{}", precision, value, units[unit])
+}
+
+fn format_duration(duration: Duration, style: DurationStyle) -> String {
+    let seconds = duration.as_secs();
+    match style {
+        DurationStyle::Minutes => format!("{}:{:02}", seconds / 60, seconds % 60),
+        DurationStyle::Hours => format!("{}:{:02}", seconds / 3600, seconds / 60 % 60),
+        DurationStyle::Human => {
+            let days = seconds / 86400;
+            let hours = seconds / 3600 % 24;
+            let minutes = seconds / 60 % 60;
+
+            if days > 0 {
+                format!("{}d {}h", days, hours)
+            } else if hours > 0 {
+                format!("{}h {}m", hours, minutes)
+            } else {
+                format!("{}m", minutes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLACEHOLDERS: &[&str] = &["text", "number", "bytes", "time"];
+    const UNITS: &[(&str, UnitKind)] = &[("bytes", UnitKind::Bytes), ("time", UnitKind::Duration)];
+
+    fn render(format: &str) -> String {
+        let values = [
+            ("text", Value::from("abc")),
+            ("number", Value::Number(-3.26, 2)),
+            ("bytes", Value::Bytes(1_572_864, UnitBase::Binary)),
+            (
+                "time",
+                Value::Duration(Duration::from_secs(3725), DurationStyle::Minutes),
+            ),
+        ];
+        let template = Template::parse_with_units(format, PLACEHOLDERS, UNITS).unwrap();
+        template.render(&values)
+    }
+
+    fn error(format: &str) -> String {
+        let error = Template::parse_with_units(format, PLACEHOLDERS, UNITS).unwrap_err();
+        error.to_string()
+    }
+
+    #[test]
+    fn parse_placeholders() {
+        assert_eq!(render("[{text}] {number}"), "[abc] -3.26");
+        assert_eq!(render("{bytes} {time}"), "1.5MiB 62:05");
+        assert_eq!(render("{text}{text}"), "abcabc");
+        assert_eq!(render(""), "");
+    }
+
+    #[test]
+    fn parse_escapes() {
+        assert_eq!(render("{{text}}"), "{text}");
+        assert_eq!(render("{{{text}}}"), "{abc}");
+        assert_eq!(render("}}{{"), "}{");
+    }
+
+    #[test]
+    fn parse_specs() {
+        assert_eq!(render("{text:>5}"), "  abc");
+        assert_eq!(render("{text:*^7}"), "**abc**");
+        assert_eq!(render("{text:.2}"), "ab");
+        assert_eq!(render("{number:.0}"), "-3");
+        assert_eq!(render("{number:>8.1}"), "    -3.3");
+        assert_eq!(render("{number:08.1}"), "-00003.3");
+        assert_eq!(render("{number:<7}|"), "-3.26  |");
+    }
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(render("{bytes:KiB}"), "1536.0KiB");
+        assert_eq!(render("{bytes:.2MB}"), "1.57MB");
+        assert_eq!(render("{time:s}"), "3725s");
+        assert_eq!(render("{time:.1h}"), "1.0h");
+        assert_eq!(render("{time:>6m}"), "   62m");
+    }
+
+    #[test]
+    fn parse_conditionals() {
+        let template = Template::parse("{?text:[{text}]}{!text:none}", PLACEHOLDERS).unwrap();
+        assert_eq!(template.render(&[("text", "abc".into())]), "[abc]");
+        assert_eq!(template.render(&[]), "none");
+    }
+
+    #[test]
+    fn missing_values() {
+        let template = Template::parse("{text}|{number:>3}|", PLACEHOLDERS).unwrap();
+        assert_eq!(template.render(&[]), "|   |");
+    }
+
+    #[test]
+    fn invalid_formats() {
+        assert_eq!(
+            error("{unknown}"),
+            "unknown placeholder `{unknown}`, expected one of {text}, {number}, {bytes}, {time} \
+             in format \"{unknown}\""
+        );
+        assert_eq!(error("{text"), "unclosed placeholder in format \"{text\"");
+        assert_eq!(error("text}"), "unmatched `}` in format \"text}\"");
+        assert_eq!(
+            error("{?text:abc"),
+            "unclosed conditional in format \"{?text:abc\""
+        );
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert_eq!(
+            error("{text:x}"),
+            "invalid spec `x` of `{text}` in format \"{text:x}\""
+        );
+        assert_eq!(
+            error("{number:5.}"),
+            "invalid spec `5.` of `{number}` in format \"{number:5.}\""
+        );
+        assert_eq!(
+            error("{bytes:kb}"),
+            "invalid spec `kb` of `{bytes}` in format \"{bytes:kb}\""
+        );
+    }
+
+    #[test]
+    fn invalid_units() {
+        assert_eq!(
+            error("{bytes:s}"),
+            "unit `s` is not supported by `{bytes}` in format \"{bytes:s}\""
+        );
+        assert_eq!(
+            error("{time:MiB}"),
+            "unit `MiB` is not supported by `{time}` in format \"{time:MiB}\""
+        );
+        assert_eq!(
+            error("{number:h}"),
+            "unit `h` is not supported by `{number}` in format \"{number:h}\""
+        );
+        assert!(Template::parse("{text:.1s}", PLACEHOLDERS).is_err());
+    }
+}
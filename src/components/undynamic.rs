@@ -1,11 +1,6 @@
-#[cfg(all(feature = "json-fmt", not(feature = "toml-fmt")))]
-use serde_json as serde_fmt;
-#[cfg(not(any(feature = "toml-fmt", feature = "json-fmt")))]
-use serde_yaml as serde_fmt;
-#[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
-use toml as serde_fmt;
-
-use crate::components::{Component, ComponentID, ComponentSettings, ComponentTrait};
+use crate::components::{
+    Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings, ComponentTrait,
+};
 
 use serde::de::Deserialize;
 
@@ -36,11 +31,14 @@ impl ComponentTrait for Undynamic {
 }
 
 impl Undynamic {
-    pub(crate) fn create(settings: ComponentSettings, extra: serde_fmt::Value) -> Component {
-        Component(Box::new(Self {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        Ok(Component::new(Self {
             settings,
             id: ComponentID::default(),
-            extra: Extra::deserialize(extra).unwrap(),
+            extra: Extra::deserialize(extra)?,
         }))
     }
 }
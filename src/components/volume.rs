@@ -0,0 +1,355 @@
+use futures::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use serde::de::Deserialize;
+use tokio::io::lines;
+use tokio::prelude::*;
+use tokio_process::CommandExt;
+
+use std::ffi::OsStr;
+use std::io::BufReader;
+use std::process::{Command as ProcessCommand, Output, Stdio};
+use std::sync::{Arc, Mutex};
+
+use crate::components::template::Template;
+use crate::components::{
+    restart_stream, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+use crate::event::{Event, MouseButton, MouseButtonState};
+
+const DEFAULT_PACTL: &str = "pactl";
+const DEFAULT_FORMAT: &str = "{volume}%";
+const DEFAULT_FORMAT_MUTED: &str = "muted";
+const DEFAULT_STEP: u32 = 5;
+const DEFAULT_MAX_VOLUME: u32 = 100;
+const DEFAULT_SINK: &str = "@DEFAULT_SINK@";
+const PLACEHOLDERS: &[&str] = &["volume"];
+
+pub struct Volume {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    format_muted: Template,
+    state: State,
+    shared_state: Arc<Mutex<Snapshot>>,
+    // Arguments of the pactl commands for clicks, which are run by the stream
+    commands: UnboundedSender<Vec<String>>,
+    commands_rx: Mutex<Option<UnboundedReceiver<Vec<String>>>>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_pactl")]
+    pactl: String,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_format_muted")]
+    format_muted: String,
+    #[serde(default = "default_step")]
+    step: u32,
+    #[serde(default = "default_max_volume")]
+    max_volume: u32,
+}
+
+fn default_pactl() -> String {
+    String::from(DEFAULT_PACTL)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_format_muted() -> String {
+    String::from(DEFAULT_FORMAT_MUTED)
+}
+
+fn default_step() -> u32 {
+    DEFAULT_STEP
+}
+
+fn default_max_volume() -> u32 {
+    DEFAULT_MAX_VOLUME
+}
+
+// Volume of the default sink in percent
+#[derive(Clone, Copy, PartialEq)]
+struct Sink {
+    volume: u32,
+    muted: bool,
+}
+
+// Latest state of the default sink reported by the sound server
+#[derive(Clone, Default, PartialEq)]
+struct Snapshot {
+    sink: Option<Sink>,
+    error: Option<String>,
+    command_error: Option<String>,
+}
+
+// Formatted volume of the default sink
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    volume: Option<f64>,
+    error: Option<String>,
+}
+
+impl ComponentTrait for Volume {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.state.volume
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let pactl = self.extra.pactl.clone();
+        let shared_state = self.shared_state.clone();
+        let command_pactl = self.extra.pactl.clone();
+        let command_state = self.shared_state.clone();
+
+        // Restart the subscription forever once pactl has exited
+        let sinks = restart_stream(move || {
+            let query_pactl = pactl.clone();
+            subscribe(&pactl)
+                .and_then(move |_| query(&query_pactl))
+                .then(Ok)
+        });
+
+        let changes = sinks.filter_map(move |sink| {
+            let mut snapshot = shared_state.lock().unwrap();
+
+            // Keep the last known volume when the sound server is unavailable
+            let new_snapshot = match sink {
+                Ok(sink) => Snapshot {
+                    sink: Some(sink),
+                    error: None,
+                    command_error: snapshot.command_error.clone(),
+                },
+                Err(error) => Snapshot {
+                    sink: snapshot.sink,
+                    error: Some(error),
+                    command_error: snapshot.command_error.clone(),
+                },
+            };
+
+            if new_snapshot != *snapshot {
+                *snapshot = new_snapshot;
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        // The sound server notifies about the new volume with a change event
+        let commands: Box<dyn Stream<Item = Vec<String>, Error = ()> + Send> =
+            match self.commands_rx.lock().unwrap().take() {
+                Some(commands_rx) => Box::new(commands_rx),
+                None => Box::new(stream::empty()),
+            };
+        let command_results = commands
+            .and_then(move |args| run(&command_pactl, &args).then(|result| Ok(result.err())))
+            .filter_map(move |command_error| {
+                let mut snapshot = command_state.lock().unwrap();
+                if command_error != snapshot.command_error {
+                    snapshot.command_error = command_error;
+                    Some(id)
+                } else {
+                    None
+                }
+            });
+
+        Box::new(changes.select(command_results))
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.read_state();
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn notify(&mut self, event: Event) -> bool {
+        match event {
+            Event::Click(button, button_state, _) => {
+                // Buttons act once released, while every step of the wheel is a press
+                let wheel = button == MouseButton::WheelUp || button == MouseButton::WheelDown;
+                if wheel != (button_state == MouseButtonState::Pressed) {
+                    return false;
+                }
+
+                let args = match self.click_args(button) {
+                    Some(args) => args,
+                    None => return false,
+                };
+
+                // Running pactl is left to the stream, so the bar's thread never waits for it
+                let _ = self.commands.unbounded_send(args);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Volume {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let (commands, commands_rx) = mpsc::unbounded();
+
+        // The volume is only available once the sound server has been queried
+        Ok(Component::new(Self {
+            settings,
+            id: ComponentID::default(),
+            format: Template::parse(&extra.format, PLACEHOLDERS)?,
+            format_muted: Template::parse(&extra.format_muted, PLACEHOLDERS)?,
+            extra,
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(Snapshot::default())),
+            commands,
+            commands_rx: Mutex::new(Some(commands_rx)),
+        }))
+    }
+
+    fn read_state(&self) -> State {
+        let snapshot = self.shared_state.lock().unwrap().clone();
+        let text = match snapshot.sink {
+            Some(sink) => {
+                let format = if sink.muted {
+                    &self.format_muted
+                } else {
+                    &self.format
+                };
+                format.render(&[("volume", u64::from(sink.volume).into())])
+            }
+            None => String::new(),
+        };
+
+        // Failed commands are reported until the next one succeeds
+        State {
+            text,
+            volume: snapshot.sink.map(|sink| f64::from(sink.volume)),
+            error: snapshot.command_error.or(snapshot.error),
+        }
+    }
+
+    // Map a mouse button to the arguments of the pactl command it should run
+    fn click_args(&self, button: MouseButton) -> Option<Vec<String>> {
+        let sink = self.shared_state.lock().unwrap().sink?;
+
+        // Volumes which have been raised above the maximum elsewhere are never lowered by
+        // scrolling up
+        let volume = match button {
+            MouseButton::WheelUp => {
+                let max_volume = self.extra.max_volume.max(sink.volume);
+                (sink.volume + self.extra.step).min(max_volume)
+            }
+            MouseButton::WheelDown => sink.volume.saturating_sub(self.extra.step),
+            MouseButton::Center => {
+                let args = ["set-sink-mute", DEFAULT_SINK, "toggle"];
+                return Some(args.iter().map(|arg| String::from(*arg)).collect());
+            }
+            _ => return None,
+        };
+
+        let args = ["set-sink-volume", DEFAULT_SINK, &format!("{}%", volume)];
+        Some(args.iter().map(|arg| String::from(*arg)).collect())
+    }
+}
+
+// Stream once initially and after every change of a sink or the default sink
+//
+// The stream ends with an error once pactl has exited.
+fn subscribe(pactl: &str) -> Box<dyn Stream<Item = (), Error = String> + Send> {
+    let child = ProcessCommand::new(pactl)
+        .arg("subscribe")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn_async();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => return Box::new(stream::once(Err(err.to_string()))),
+    };
+
+    // Events look like `Event 'change' on sink #0`, the server changes with the default sink
+    let stdout = child.stdout().take().unwrap();
+    let events = lines(BufReader::new(stdout))
+        .map_err(|err| err.to_string())
+        .filter(|line| line.contains(" on sink #") || line.contains(" on server"))
+        .map(|_| ());
+    let exit = child.then(|status| match status {
+        Ok(status) => Err(format!("pactl subscribe exited with {}", status)),
+        Err(err) => Err(err.to_string()),
+    });
+
+    Box::new(stream::once(Ok(())).chain(events).chain(exit.into_stream()))
+}
+
+// Query the volume and mute state of the default sink
+fn query(pactl: &str) -> impl Future<Item = Sink, Error = String> {
+    let volume = run(pactl, &["get-sink-volume", DEFAULT_SINK]);
+    let muted = run(pactl, &["get-sink-mute", DEFAULT_SINK]);
+    volume.join(muted).and_then(|(volume, muted)| {
+        // Output looks like `Volume: front-left: 32768 /  50% / -18.06 dB,   front-right: ...`
+        let channels = volume
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter_map(|part| part.trim().strip_suffix('%')?.parse::<u32>().ok())
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            return Err(String::from("invalid pactl volume"));
+        }
+
+        // Channels with different volumes are shown by their average
+        Ok(Sink {
+            volume: channels.iter().sum::<u32>() / channels.len() as u32,
+            muted: muted.trim() == "Mute: yes",
+        })
+    })
+}
+
+fn run<S: AsRef<OsStr>>(pactl: &str, args: &[S]) -> impl Future<Item = String, Error = String> {
+    ProcessCommand::new(pactl)
+        .args(args)
+        .stdin(Stdio::null())
+        .output_async()
+        .map_err(|err| err.to_string())
+        .and_then(stdout_from_output)
+}
+
+fn stdout_from_output(output: Output) -> Result<String, String> {
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+
+    // pactl explains failures like a missing server on stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().next() {
+        Some(line) if !line.is_empty() => Err(line.to_owned()),
+        _ => Err(format!("pactl exited with {}", output.status)),
+    }
+}
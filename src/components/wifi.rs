@@ -0,0 +1,308 @@
+use serde::de::Deserialize;
+use tokio::prelude::*;
+use tokio::timer::Interval;
+use tokio_process::CommandExt;
+
+use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::{Command as ProcessCommand, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::components::command::default_shell;
+use crate::components::nl80211;
+use crate::components::template::{Template, Value};
+use crate::components::{
+    quote, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+
+const DEFAULT_PROC: &str = "/proc";
+const DEFAULT_FORMAT: &str = "{ssid} {quality}%";
+const DEFAULT_FORMAT_DOWN: &str = "{interface} down";
+const DEFAULT_SSID_COMMAND: &str = "iw dev {interface} link";
+const DEFAULT_INTERVAL_MILLIS: u64 = 5000;
+
+const PLACEHOLDERS: &[&str] = &["interface", "ssid", "quality", "signal", "icon"];
+const PLACEHOLDERS_DOWN: &[&str] = &["interface"];
+
+// Link quality reported by most drivers is out of 70
+const MAX_LINK_QUALITY: f64 = 70.;
+
+pub struct Wifi {
+    id: ComponentID,
+    settings: ComponentSettings,
+    reader: Arc<Reader>,
+    state: State,
+    shared_state: Arc<Mutex<State>>,
+}
+
+// Everything required for reading the state on the event loop
+struct Reader {
+    extra: Extra,
+    format: Template,
+    format_down: Template,
+    ssid_command: Template,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    #[serde(default = "default_proc")]
+    proc: PathBuf,
+    interface: Option<String>,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_format_down")]
+    format_down: String,
+    #[serde(default)]
+    icons: Vec<String>,
+    #[serde(default = "default_ssid_command")]
+    ssid_command: String,
+    #[serde(default = "default_shell")]
+    shell: String,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_proc() -> PathBuf {
+    PathBuf::from(DEFAULT_PROC)
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_format_down() -> String {
+    String::from(DEFAULT_FORMAT_DOWN)
+}
+
+fn default_ssid_command() -> String {
+    String::from(DEFAULT_SSID_COMMAND)
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MILLIS
+}
+
+// Formatted state of the wireless connection
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    quality: Option<f64>,
+    error: Option<String>,
+}
+
+// Link information of a wireless interface
+struct Link {
+    interface: String,
+    quality: f64,
+    signal: f64,
+}
+
+impl ComponentTrait for Wifi {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.state.quality
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let reader = self.reader.clone();
+        let shared_state = self.shared_state.clone();
+
+        let interval = Duration::from_millis(self.reader.extra.interval);
+        let task = Interval::new(Instant::now(), interval)
+            .map_err(|_| ())
+            .and_then(move |_| reader.clone().read_state())
+            .filter_map(move |new_state| {
+                let mut state = shared_state.lock().unwrap();
+
+                // Only request an update if the state has changed
+                if new_state != *state {
+                    *state = new_state;
+                    Some(id)
+                } else {
+                    None
+                }
+            });
+
+        Box::new(task)
+    }
+
+    fn update(&mut self) -> bool {
+        let state = self.shared_state.lock().unwrap();
+        if *state != self.state {
+            self.state = state.clone();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Wifi {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let reader = Reader {
+            format: Template::parse(&extra.format, PLACEHOLDERS)?,
+            format_down: Template::parse(&extra.format_down, PLACEHOLDERS_DOWN)?,
+            ssid_command: Template::parse(&extra.ssid_command, PLACEHOLDERS_DOWN)?,
+            extra,
+        };
+
+        Ok(Component::new(Self {
+            settings,
+            id: ComponentID::default(),
+            reader: Arc::new(reader),
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(State::default())),
+        }))
+    }
+}
+
+impl Reader {
+    fn read_state(self: Arc<Self>) -> Box<dyn Future<Item = State, Error = ()> + Send> {
+        let path = self.extra.proc.join("net/wireless");
+        let link = match read_wireless(&path, self.extra.interface.as_deref()) {
+            Ok(Some(link)) => link,
+            Ok(None) => {
+                let interface = self.extra.interface.clone().unwrap_or_default();
+                return Box::new(future::ok(State {
+                    text: self.format_down.render(&[("interface", interface.into())]),
+                    quality: None,
+                    error: None,
+                }));
+            }
+            Err(err) => {
+                return Box::new(future::ok(State {
+                    text: String::new(),
+                    quality: None,
+                    error: Some(err.to_string()),
+                }))
+            }
+        };
+
+        // Fall back to the command if netlink is not available
+        match nl80211::ssid(&link.interface) {
+            Ok(ssid) => Box::new(future::ok(self.render(link, ssid, None))),
+            Err(_) => Box::new(self.command_ssid(&link.interface).then(move |ssid| {
+                Ok(match ssid {
+                    Ok(ssid) => self.render(link, ssid, None),
+                    Err(err) => self.render(link, None, Some(err.to_string())),
+                })
+            })),
+        }
+    }
+
+    // Format the link with its SSID
+    fn render(&self, link: Link, ssid: Option<String>, error: Option<String>) -> State {
+        let quality = (link.quality * 100. / MAX_LINK_QUALITY)
+            .round()
+            .clamp(0., 100.);
+        if quality == 0. && ssid.is_none() {
+            let values = [("interface", link.interface.into())];
+            return State {
+                text: self.format_down.render(&values),
+                quality: None,
+                error,
+            };
+        }
+
+        let mut values = vec![
+            ("interface", link.interface.into()),
+            ("quality", Value::Number(quality, 0)),
+            ("signal", Value::Number(link.signal, 0)),
+        ];
+        if let Some(ssid) = ssid {
+            values.push(("ssid", ssid.into()));
+        }
+
+        // Icons are spread evenly across the quality range
+        if !self.extra.icons.is_empty() {
+            let len = self.extra.icons.len();
+            let index = (quality as usize * len / 100).min(len - 1);
+            values.push(("icon", self.extra.icons[index].as_str().into()));
+        }
+
+        State {
+            text: self.format.render(&values),
+            quality: Some(quality),
+            error,
+        }
+    }
+
+    // Read the SSID from the output of a command like `iw dev wlan0 link`
+    fn command_ssid(&self, interface: &str) -> impl Future<Item = Option<String>, Error = IOError> {
+        let command = self
+            .ssid_command
+            .render(&[("interface", quote(interface).into())]);
+        ProcessCommand::new(&self.extra.shell)
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output_async()
+            .map(|output| {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                stdout
+                    .lines()
+                    .filter_map(|line| line.trim().strip_prefix("SSID:"))
+                    .map(|ssid| ssid.trim().to_owned())
+                    .next()
+            })
+    }
+}
+
+// Read the link of an interface, or the first wireless interface
+fn read_wireless(path: &Path, interface: Option<&str>) -> Result<Option<Link>, IOError> {
+    let wireless = fs::read_to_string(path)?;
+
+    for line in wireless.lines().skip(2) {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if interface.map_or(false, |interface| interface != name) {
+            continue;
+        }
+
+        // Values are followed by a `.` if they have been updated since the last read
+        let fields = parts
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .skip(1)
+            .take(2)
+            .map(|field| field.trim_end_matches('.').parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| IOError::new(ErrorKind::InvalidData, err))?;
+
+        return match fields.as_slice() {
+            [quality, signal] => Ok(Some(Link {
+                interface: name.to_owned(),
+                quality: *quality,
+                signal: *signal,
+            })),
+            _ => Err(IOError::new(ErrorKind::InvalidData, "invalid wireless")),
+        };
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,300 @@
+use serde::de::Deserialize;
+use tokio::prelude::*;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::components::template::Template;
+use crate::components::{ipc, json};
+use crate::components::{
+    Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings, ComponentStream,
+    ComponentTrait,
+};
+use crate::config::Monitor;
+
+const DEFAULT_FORMAT: &str = "{title}";
+const DEFAULT_ELLIPSIS: &str = "…";
+const PLACEHOLDERS: &[&str] = &["title", "app", "output"];
+
+pub struct WindowTitle {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    format: Template,
+    monitors: Vec<Monitor>,
+    state: State,
+    shared_state: Arc<Mutex<Snapshot>>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    socket: Option<PathBuf>,
+    #[serde(default = "default_format")]
+    format: String,
+    max_length: Option<usize>,
+    #[serde(default = "default_ellipsis")]
+    ellipsis: String,
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_ellipsis() -> String {
+    String::from(DEFAULT_ELLIPSIS)
+}
+
+// Container in the layout tree of the window manager
+#[derive(Deserialize)]
+struct Node {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    #[serde(default)]
+    focused: bool,
+    #[serde(default)]
+    focus: Vec<i64>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+    app_id: Option<String>,
+    window_properties: Option<WindowProperties>,
+}
+
+#[derive(Deserialize)]
+struct WindowProperties {
+    class: Option<String>,
+}
+
+// Window which has the focus on its output
+#[derive(Clone, PartialEq)]
+struct Window {
+    output: String,
+    title: Option<String>,
+    app: Option<String>,
+    focused: bool,
+}
+
+// Latest windows received from the window manager
+#[derive(Clone, Default, PartialEq)]
+struct Snapshot {
+    windows: Vec<Window>,
+    error: Option<String>,
+}
+
+// Formatted title of the focused window
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+}
+
+impl ComponentTrait for WindowTitle {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let path = match self.extra.socket.clone().or_else(ipc::socket_path) {
+            Some(path) => path,
+            None => return Box::new(stream::empty()),
+        };
+        let shared_state = self.shared_state.clone();
+
+        // Switching to an empty workspace does not emit any window event
+        let replies = ipc::watch(path, &["window", "workspace"], ipc::GET_TREE);
+        let task = replies.filter_map(move |reply| {
+            let windows = reply.and_then(|reply| {
+                json::from_slice::<Node>(&reply.payload)
+                    .map(|tree| focused_windows(&tree))
+                    .map_err(|err| err.to_string())
+            });
+
+            let mut snapshot = shared_state.lock().unwrap();
+
+            // Keep the last known windows when the connection breaks
+            let new_snapshot = match windows {
+                Ok(windows) => Snapshot {
+                    windows,
+                    error: None,
+                },
+                Err(error) => Snapshot {
+                    windows: snapshot.windows.clone(),
+                    error: Some(error),
+                },
+            };
+
+            if new_snapshot != *snapshot {
+                *snapshot = new_snapshot;
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        Box::new(task)
+    }
+
+    fn update(&mut self) -> bool {
+        self.render()
+    }
+
+    fn set_monitors(&mut self, monitors: &[Monitor]) -> bool {
+        self.monitors = monitors.to_vec();
+        self.render()
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl WindowTitle {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let format = Template::parse(&extra.format, PLACEHOLDERS)?;
+
+        // The title is only available once the connection has been established
+        let mut snapshot = Snapshot::default();
+        if extra.socket.is_none() && ipc::socket_path().is_none() {
+            snapshot.error = Some(String::from("no i3 or sway IPC socket found"));
+        }
+
+        let mut window_title = Self {
+            settings,
+            id: ComponentID::default(),
+            extra,
+            format,
+            monitors: Vec::new(),
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(snapshot)),
+        };
+        window_title.render();
+
+        Ok(Component::new(window_title))
+    }
+
+    // Format the latest title, returning `true` if the state has changed
+    fn render(&mut self) -> bool {
+        let snapshot = self.shared_state.lock().unwrap().clone();
+        let available = snapshot
+            .windows
+            .iter()
+            .map(|window| window.output.as_str())
+            .collect::<Vec<_>>();
+
+        // Prefer the focused window, falling back to the window focused on the first monitor
+        let window = match ipc::resolve_outputs(&self.monitors, &available) {
+            Some(outputs) => {
+                let windows = outputs
+                    .iter()
+                    .filter_map(|output| snapshot.windows.iter().find(|w| &w.output == output))
+                    .collect::<Vec<_>>();
+                windows
+                    .iter()
+                    .find(|window| window.focused)
+                    .or_else(|| windows.first())
+                    .cloned()
+            }
+            None => snapshot.windows.iter().find(|window| window.focused),
+        };
+
+        let text = match window {
+            Some(Window {
+                title: Some(title),
+                app,
+                output,
+                ..
+            }) => {
+                let mut values = vec![
+                    ("title", self.truncate(title).into()),
+                    ("output", output.as_str().into()),
+                ];
+                if let Some(app) = app {
+                    values.push(("app", app.as_str().into()));
+                }
+                self.format.render(&values)
+            }
+            _ => String::new(),
+        };
+
+        let state = State {
+            text,
+            error: snapshot.error,
+        };
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Shorten the title to the maximum length, including the ellipsis
+    fn truncate(&self, title: &str) -> String {
+        let max_length = match self.extra.max_length {
+            Some(max_length) if title.chars().count() > max_length => max_length,
+            _ => return title.to_owned(),
+        };
+
+        let len = max_length.saturating_sub(self.extra.ellipsis.chars().count());
+        let mut title = title.chars().take(len).collect::<String>();
+        title.push_str(&self.extra.ellipsis);
+        title
+    }
+}
+
+// Find the window which has the focus on every output
+fn focused_windows(tree: &Node) -> Vec<Window> {
+    tree.nodes
+        .iter()
+        .filter(|output| output.kind == "output")
+        .filter_map(|output| {
+            let name = output.name.as_ref()?;
+
+            // Internal outputs like i3's scratchpad are prefixed with two underscores
+            if name.starts_with("__") {
+                return None;
+            }
+
+            // Follow the focus down to the window which was focused last
+            let mut node = output;
+            while let Some(child) = node.focus.first().and_then(|id| {
+                node.nodes
+                    .iter()
+                    .chain(&node.floating_nodes)
+                    .find(|child| child.id == *id)
+            }) {
+                node = child;
+            }
+
+            // Empty workspaces can still have the focus
+            let is_window = node.kind == "con" || node.kind == "floating_con";
+            let app = node.app_id.clone().or_else(|| {
+                let properties = node.window_properties.as_ref()?;
+                properties.class.clone()
+            });
+
+            Some(Window {
+                output: name.clone(),
+                title: node.name.clone().filter(|_| is_window),
+                app: app.filter(|_| is_window),
+                focused: node.focused,
+            })
+        })
+        .collect()
+}
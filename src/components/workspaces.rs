@@ -0,0 +1,319 @@
+use futures::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use serde::de::Deserialize;
+use tokio::prelude::*;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::components::template::Template;
+use crate::components::{ipc, json};
+use crate::components::{
+    quote, Component, ComponentError, ComponentID, ComponentOptions, ComponentSettings,
+    ComponentStream, ComponentTrait,
+};
+use crate::config::Monitor;
+use crate::event::{ComponentPosition, Event, MouseButton, MouseButtonState};
+
+const DEFAULT_FORMAT: &str = "{name}";
+const DEFAULT_FORMAT_FOCUSED: &str = "[{name}]";
+const DEFAULT_SEPARATOR: &str = " ";
+const PLACEHOLDERS: &[&str] = &["name", "num", "output"];
+
+pub struct Workspaces {
+    id: ComponentID,
+    settings: ComponentSettings,
+    extra: Extra,
+    formats: Formats,
+    monitors: Vec<Monitor>,
+    state: State,
+    shared_state: Arc<Mutex<Snapshot>>,
+    commands: UnboundedSender<String>,
+    commands_rx: Mutex<Option<UnboundedReceiver<String>>>,
+}
+
+#[derive(Deserialize)]
+struct Extra {
+    socket: Option<PathBuf>,
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default = "default_format_focused")]
+    format_focused: String,
+    format_visible: Option<String>,
+    format_urgent: Option<String>,
+    #[serde(default = "default_separator")]
+    separator: String,
+}
+
+fn default_format() -> String {
+    String::from(DEFAULT_FORMAT)
+}
+
+fn default_format_focused() -> String {
+    String::from(DEFAULT_FORMAT_FOCUSED)
+}
+
+fn default_separator() -> String {
+    String::from(DEFAULT_SEPARATOR)
+}
+
+// Parsed formats for every kind of workspace
+struct Formats {
+    normal: Template,
+    focused: Template,
+    visible: Option<Template>,
+    urgent: Option<Template>,
+}
+
+// Workspace as reported by the window manager
+#[derive(Deserialize, Clone, PartialEq)]
+struct Workspace {
+    num: i32,
+    name: String,
+    visible: bool,
+    focused: bool,
+    urgent: bool,
+    output: String,
+}
+
+// Latest workspaces received from the window manager
+#[derive(Clone, Default, PartialEq)]
+struct Snapshot {
+    workspaces: Vec<Workspace>,
+    error: Option<String>,
+    command_error: Option<String>,
+}
+
+// Formatted workspaces of the bar's monitors
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    text: String,
+    error: Option<String>,
+    labels: Vec<Label>,
+}
+
+// Characters of the text occupied by a workspace
+#[derive(Clone, PartialEq)]
+struct Label {
+    start: usize,
+    end: usize,
+    name: String,
+}
+
+impl ComponentTrait for Workspaces {
+    fn text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.error.clone()
+    }
+
+    fn stream(&self) -> ComponentStream {
+        let id = self.id();
+        let path = match self.extra.socket.clone().or_else(ipc::socket_path) {
+            Some(path) => path,
+            None => return Box::new(stream::empty()),
+        };
+        let shared_state = self.shared_state.clone();
+        let command_state = self.shared_state.clone();
+        let command_path = path.clone();
+
+        let replies = ipc::watch(path, &["workspace"], ipc::GET_WORKSPACES);
+        let changes = replies.filter_map(move |reply| {
+            let workspaces = reply.and_then(|reply| {
+                json::from_slice::<Vec<Workspace>>(&reply.payload).map_err(|err| err.to_string())
+            });
+
+            let mut snapshot = shared_state.lock().unwrap();
+
+            // Keep the last known workspaces when the connection breaks
+            let new_snapshot = match workspaces {
+                Ok(workspaces) => Snapshot {
+                    workspaces,
+                    error: None,
+                    command_error: snapshot.command_error.clone(),
+                },
+                Err(error) => Snapshot {
+                    workspaces: snapshot.workspaces.clone(),
+                    error: Some(error),
+                    command_error: snapshot.command_error.clone(),
+                },
+            };
+
+            if new_snapshot != *snapshot {
+                *snapshot = new_snapshot;
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        // Workspaces are switched one after another, each on a new connection
+        let commands: Box<dyn Stream<Item = String, Error = ()> + Send> =
+            match self.commands_rx.lock().unwrap().take() {
+                Some(commands_rx) => Box::new(commands_rx),
+                None => Box::new(stream::empty()),
+            };
+        let command_results = commands
+            .and_then(move |name| {
+                let command = format!("workspace {}", quote(&name));
+                ipc::run_command(&command_path, &command)
+                    .then(|result| Ok(result.err().map(|e| e.to_string())))
+            })
+            .filter_map(move |command_error| {
+                let mut snapshot = command_state.lock().unwrap();
+                if command_error != snapshot.command_error {
+                    snapshot.command_error = command_error;
+                    Some(id)
+                } else {
+                    None
+                }
+            });
+
+        Box::new(changes.select(command_results))
+    }
+
+    fn update(&mut self) -> bool {
+        self.render()
+    }
+
+    fn notify_at(&mut self, event: Event, pos: ComponentPosition) -> bool {
+        match event {
+            Event::Click(MouseButton::Left, MouseButtonState::Released, point) => {
+                if !pos.contains(point) {
+                    return false;
+                }
+
+                // The frontend only reports the position of the whole component, so labels are
+                // located assuming every character has the same width. With proportional fonts,
+                // clicks close to the edge of a label might select its neighbour.
+                let len = self.state.text.chars().count();
+                let index = (point.x as usize - pos.min_x) * len / (pos.max_x - pos.min_x + 1);
+                let label = self
+                    .state
+                    .labels
+                    .iter()
+                    .find(|label| index >= label.start && index < label.end);
+
+                // Switching is left to the stream, so the bar's thread never waits for the IPC
+                if let Some(label) = label {
+                    let _ = self.commands.unbounded_send(label.name.clone());
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn set_monitors(&mut self, monitors: &[Monitor]) -> bool {
+        self.monitors = monitors.to_vec();
+        self.render()
+    }
+
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+}
+
+impl Workspaces {
+    pub(crate) fn create(
+        settings: ComponentSettings,
+        extra: ComponentOptions,
+    ) -> Result<Component, ComponentError> {
+        let extra = Extra::deserialize(extra)?;
+        let parse = |format: &Option<String>| {
+            format
+                .as_ref()
+                .map(|format| Template::parse(format, PLACEHOLDERS))
+                .transpose()
+        };
+        let formats = Formats {
+            normal: Template::parse(&extra.format, PLACEHOLDERS)?,
+            focused: Template::parse(&extra.format_focused, PLACEHOLDERS)?,
+            visible: parse(&extra.format_visible)?,
+            urgent: parse(&extra.format_urgent)?,
+        };
+
+        // Workspaces are only available once the connection has been established
+        let mut snapshot = Snapshot::default();
+        if extra.socket.is_none() && ipc::socket_path().is_none() {
+            snapshot.error = Some(String::from("no i3 or sway IPC socket found"));
+        }
+
+        let (commands, commands_rx) = mpsc::unbounded();
+        let mut workspaces = Self {
+            settings,
+            id: ComponentID::default(),
+            extra,
+            formats,
+            monitors: Vec::new(),
+            state: State::default(),
+            shared_state: Arc::new(Mutex::new(snapshot)),
+            commands,
+            commands_rx: Mutex::new(Some(commands_rx)),
+        };
+        workspaces.render();
+
+        Ok(Component::new(workspaces))
+    }
+
+    // Format the latest workspaces, returning `true` if the state has changed
+    fn render(&mut self) -> bool {
+        let snapshot = self.shared_state.lock().unwrap().clone();
+
+        // Every active output always contains at least one workspace
+        let available = snapshot
+            .workspaces
+            .iter()
+            .map(|workspace| workspace.output.as_str())
+            .collect::<Vec<_>>();
+        let outputs = ipc::resolve_outputs(&self.monitors, &available);
+
+        let mut state = State {
+            error: snapshot.command_error.or(snapshot.error),
+            ..State::default()
+        };
+        let workspaces = snapshot.workspaces.iter().filter(|workspace| {
+            outputs
+                .as_ref()
+                .map_or(true, |outputs| outputs.contains(&workspace.output))
+        });
+        for workspace in workspaces {
+            let format = match (&self.formats.urgent, &self.formats.visible) {
+                (Some(format_urgent), _) if workspace.urgent => format_urgent,
+                _ if workspace.focused => &self.formats.focused,
+                (_, Some(format_visible)) if workspace.visible => format_visible,
+                _ => &self.formats.normal,
+            };
+
+            let values = [
+                ("name", workspace.name.as_str().into()),
+                ("num", i64::from(workspace.num).into()),
+                ("output", workspace.output.as_str().into()),
+            ];
+            let label = format.render(&values);
+
+            if !state.labels.is_empty() {
+                state.text.push_str(&self.extra.separator);
+            }
+            let start = state.text.chars().count();
+            state.text.push_str(&label);
+            state.labels.push(Label {
+                start,
+                end: start + label.chars().count(),
+                name: workspace.name.clone(),
+            });
+        }
+
+        if state != self.state {
+            self.state = state;
+            true
+        } else {
+            false
+        }
+    }
+}
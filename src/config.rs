@@ -5,11 +5,17 @@ use serde_yaml as serde_fmt;
 #[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
 use toml as serde_fmt;
 
-use image::{self, DynamicImage};
+use image::{self, DynamicImage, GenericImage};
 use serde::de::{Deserializer, Error};
 use serde::Deserialize;
 
-use std::path::Path;
+use std::path::PathBuf;
+
+use crate::components::ComponentState;
+use crate::error::Error as ConfigError;
+use crate::event::MouseButton;
+
+const DEFAULT_TOOLTIP_DELAY_MILLIS: u64 = 500;
 
 /// Root element of the bar configuration file.
 #[derive(Deserialize)]
@@ -18,15 +24,14 @@ pub(crate) struct Config {
     #[serde(default)]
     pub position: Position,
     #[serde(default)]
-    pub background: Background,
+    pub background: BackgroundSource,
     pub border: Option<Border>,
-    #[serde(
-        deserialize_with = "deserialize_monitors",
-        skip_serializing_if = "Vec::is_empty"
-    )]
+    #[serde(default = "default_tooltip_delay")]
+    pub tooltip_delay: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub monitors: Vec<Monitor>,
     #[serde(default)]
-    pub defaults: ComponentSettings,
+    pub defaults: SettingsSource,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub left: Vec<Component>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -35,31 +40,35 @@ pub(crate) struct Config {
     pub right: Vec<Component>,
 }
 
-// Require at least one monitor
-fn deserialize_monitors<'a, D>(deserializer: D) -> Result<Vec<Monitor>, D::Error>
-where
-    D: Deserializer<'a>,
-{
-    match Vec::<Monitor>::deserialize(deserializer) {
-        Ok(monitors) => {
-            if monitors.is_empty() {
-                Err(D::Error::custom(String::from(
-                    "at least one monitor is required",
-                )))
-            } else {
-                Ok(monitors)
-            }
+fn default_tooltip_delay() -> u64 {
+    DEFAULT_TOOLTIP_DELAY_MILLIS
+}
+
+impl Config {
+    // Check all constraints which can not be expressed through the config's types
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // Require at least one monitor
+        if self.monitors.is_empty() {
+            return Err(ConfigError::Validation(String::from(
+                "at least one monitor is required",
+            )));
         }
-        err => err,
+
+        Ok(())
     }
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, PartialEq, Deserialize)]
 pub(crate) struct Component {
     #[serde(default)]
     pub name: String,
+    pub tooltip: Option<String>,
+    #[serde(default)]
+    pub states: StatesSource,
+    #[serde(flatten)]
+    pub settings: SettingsSource,
     #[serde(flatten)]
-    pub settings: ComponentSettings,
+    pub actions: Actions,
     #[serde(flatten)]
     pub extra: serde_fmt::Value,
 }
@@ -69,7 +78,7 @@ pub(crate) struct Component {
 /// These component settings represent most of the component's state required to draw it. All
 /// components automatically inherit the default configuration options from the bar as fallbacks,
 /// however all fields are still optional.
-#[derive(Clone, Deserialize, Default)]
+#[derive(Clone, PartialEq, Deserialize, Default)]
 pub struct ComponentSettings {
     pub foreground: Option<Color>,
     pub background: Option<Background>,
@@ -81,8 +90,21 @@ pub struct ComponentSettings {
     pub fonts: Vec<Font>,
 }
 
-impl ComponentSettings {
-    pub(crate) fn fallback(&mut self, fallback: &ComponentSettings) {
+/// Component settings as specified in the configuration file, before loading any images.
+#[derive(Clone, PartialEq, Deserialize, Default)]
+pub(crate) struct SettingsSource {
+    pub foreground: Option<Color>,
+    pub background: Option<BackgroundSource>,
+    pub width: Option<u8>,
+    pub padding: Option<u8>,
+    pub offset_x: Option<i8>,
+    pub offset_y: Option<i8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fonts: Vec<Font>,
+}
+
+impl SettingsSource {
+    pub fn fallback(&mut self, fallback: &SettingsSource) {
         fn select<T: Clone>(main: &mut Option<T>, fallback: &Option<T>) {
             if main.is_none() {
                 *main = fallback.clone();
@@ -98,6 +120,123 @@ impl ComponentSettings {
 
         self.fonts.append(&mut fallback.fonts.clone());
     }
+
+    pub fn load(self) -> Result<ComponentSettings, ConfigError> {
+        Ok(ComponentSettings {
+            foreground: self.foreground,
+            background: self.background.map(BackgroundSource::load).transpose()?,
+            width: self.width,
+            padding: self.padding,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            fonts: self.fonts,
+        })
+    }
+}
+
+/// Shell commands which are run when a component is clicked or scrolled.
+#[derive(Clone, PartialEq, Deserialize, Default)]
+pub(crate) struct Actions {
+    pub on_click_left: Option<String>,
+    pub on_click_right: Option<String>,
+    pub on_click_middle: Option<String>,
+    pub on_scroll_up: Option<String>,
+    pub on_scroll_down: Option<String>,
+    #[serde(default)]
+    pub refresh_on_action: bool,
+}
+
+impl Actions {
+    pub fn command(&self, button: MouseButton) -> Option<&str> {
+        let command = match button {
+            MouseButton::Left => &self.on_click_left,
+            MouseButton::Right => &self.on_click_right,
+            MouseButton::Center => &self.on_click_middle,
+            MouseButton::WheelUp => &self.on_scroll_up,
+            MouseButton::WheelDown => &self.on_scroll_down,
+        };
+        command.as_deref()
+    }
+}
+
+/// Component states as specified in the configuration file, before loading any images.
+#[derive(Clone, PartialEq, Deserialize, Default)]
+pub(crate) struct StatesSource {
+    pub good: Option<StateSource>,
+    pub warning: Option<StateSource>,
+    pub critical: Option<StateSource>,
+}
+
+impl StatesSource {
+    // Load all configured states, ordered by their priority
+    pub fn load(self) -> Result<Vec<StateSettings>, ConfigError> {
+        let states = vec![
+            (ComponentState::Critical, self.critical),
+            (ComponentState::Warning, self.warning),
+            (ComponentState::Good, self.good),
+        ];
+
+        states
+            .into_iter()
+            .filter_map(|(state, source)| source.map(|source| source.load(state)))
+            .collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub(crate) struct StateSource {
+    pub below: Option<f64>,
+    pub above: Option<f64>,
+    pub foreground: Option<Color>,
+    pub background: Option<BackgroundSource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fonts: Vec<Font>,
+}
+
+impl StateSource {
+    fn load(self, state: ComponentState) -> Result<StateSettings, ConfigError> {
+        Ok(StateSettings {
+            state,
+            below: self.below,
+            above: self.above,
+            foreground: self.foreground,
+            background: self.background.map(BackgroundSource::load).transpose()?,
+            fonts: self.fonts,
+        })
+    }
+}
+
+/// Thresholds of a component state and the settings which are overridden while it is active.
+#[derive(Clone, PartialEq)]
+pub(crate) struct StateSettings {
+    pub state: ComponentState,
+    pub below: Option<f64>,
+    pub above: Option<f64>,
+    pub foreground: Option<Color>,
+    pub background: Option<Background>,
+    pub fonts: Vec<Font>,
+}
+
+impl StateSettings {
+    // States without any thresholds match every value
+    pub fn matches(&self, value: f64) -> bool {
+        self.below.map_or(true, |below| value < below)
+            && self.above.map_or(true, |above| value > above)
+    }
+
+    pub fn apply(&self, settings: &ComponentSettings) -> ComponentSettings {
+        let mut settings = settings.clone();
+        if let Some(foreground) = self.foreground {
+            settings.foreground = Some(foreground);
+        }
+        if let Some(ref background) = self.background {
+            settings.background = Some(background.clone());
+        }
+        if !self.fonts.is_empty() {
+            settings.fonts = self.fonts.clone();
+        }
+        settings
+    }
 }
 
 /// Background of a component or the bar.
@@ -107,14 +246,65 @@ pub enum Background {
     Color(Color),
 }
 
+// Images are equal if they have the same size and content
+impl PartialEq for Background {
+    fn eq(&self, other: &Background) -> bool {
+        match (self, other) {
+            (Background::Color(color), Background::Color(other_color)) => color == other_color,
+            (Background::Image(img), Background::Image(other_img)) => {
+                img.dimensions() == other_img.dimensions()
+                    && img.raw_pixels() == other_img.raw_pixels()
+            }
+            _ => false,
+        }
+    }
+}
+
 impl Default for Background {
     fn default() -> Self {
         Background::Color(Color::new(0, 0, 0, 0))
     }
 }
 
+/// Background as specified in the configuration file, before loading the image.
+#[derive(Clone, PartialEq)]
+pub(crate) enum BackgroundSource {
+    Image(PathBuf),
+    Color(Color),
+}
+
+impl BackgroundSource {
+    pub fn load(self) -> Result<Background, ConfigError> {
+        match self {
+            BackgroundSource::Color(color) => Ok(Background::Color(color)),
+            BackgroundSource::Image(path) => match image::open(&path) {
+                Ok(img) => Ok(Background::Image(img)),
+                Err(error) => Err(ConfigError::Image { path, error }),
+            },
+        }
+    }
+}
+
+impl Default for BackgroundSource {
+    fn default() -> Self {
+        BackgroundSource::Color(Color::new(0, 0, 0, 0))
+    }
+}
+
+// Images are loaded immediately, the bar itself loads them through the `BackgroundSource`
 impl<'de> Deserialize<'de> for Background {
     fn deserialize<D>(deserializer: D) -> Result<Background, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BackgroundSource::deserialize(deserializer)?
+            .load()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for BackgroundSource {
+    fn deserialize<D>(deserializer: D) -> Result<BackgroundSource, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -123,12 +313,9 @@ impl<'de> Deserialize<'de> for Background {
                 if text.starts_with('#') {
                     Color::from_str(&text)
                         .map_err(D::Error::custom)
-                        .map(Background::Color)
+                        .map(BackgroundSource::Color)
                 } else {
-                    let path = Path::new(&text).canonicalize().map_err(D::Error::custom)?;
-                    image::open(path)
-                        .map(Background::Image)
-                        .map_err(D::Error::custom)
+                    Ok(BackgroundSource::Image(PathBuf::from(text)))
                 }
             }
             Err(err) => Err(err),
@@ -0,0 +1,129 @@
+#[cfg(all(feature = "json-fmt", not(feature = "toml-fmt")))]
+use serde_json as serde_fmt;
+#[cfg(not(any(feature = "toml-fmt", feature = "json-fmt")))]
+use serde_yaml as serde_fmt;
+#[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
+use toml as serde_fmt;
+
+use image::ImageError;
+
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io::Error as IOError;
+use std::path::PathBuf;
+
+use crate::bar::Alignment;
+use crate::components::ComponentError;
+
+/// Error while loading the bar configuration.
+///
+/// This error is returned when loading or reloading the bar configuration and describes why the
+/// configuration could not be used.
+#[derive(Debug)]
+pub enum Error {
+    /// The configuration could not be read.
+    Io(IOError),
+
+    /// The configuration is not in a valid format.
+    ///
+    /// The `line` and `column` are both starting at `1` and point to the location of the error in
+    /// the configuration file, if it is known.
+    Parse {
+        message: String,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+
+    /// The configuration could be parsed, but its content is not valid.
+    Validation(String),
+
+    /// An image referenced in the configuration could not be loaded.
+    Image { path: PathBuf, error: ImageError },
+
+    /// The options of a component are invalid.
+    ///
+    /// The `index` is the position of the component in the `alignment` container of the
+    /// configuration file, starting at `0`.
+    Component {
+        alignment: Alignment,
+        index: usize,
+        error: ComponentError,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "unable to read config: {}", err),
+            Error::Parse { message, .. } => write!(f, "invalid config: {}", message),
+            Error::Validation(message) => write!(f, "invalid config: {}", message),
+            Error::Image { path, error } => {
+                write!(f, "unable to load image {}: {}", path.display(), error)
+            }
+            Error::Component {
+                alignment,
+                index,
+                error,
+            } => write!(f, "invalid options in {}[{}]: {}", alignment, index, error),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Image { error, .. } => Some(error),
+            Error::Component { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<IOError> for Error {
+    fn from(err: IOError) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(not(any(feature = "toml-fmt", feature = "json-fmt")))]
+impl From<serde_fmt::Error> for Error {
+    fn from(err: serde_fmt::Error) -> Self {
+        let location = err.location();
+        Error::Parse {
+            message: err.to_string(),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+        }
+    }
+}
+
+#[cfg(all(feature = "json-fmt", not(feature = "toml-fmt")))]
+impl From<serde_fmt::Error> for Error {
+    fn from(err: serde_fmt::Error) -> Self {
+        // Errors without location use line 0
+        let (line, column) = match err.line() {
+            0 => (None, None),
+            line => (Some(line), Some(err.column())),
+        };
+
+        Error::Parse {
+            message: err.to_string(),
+            line,
+            column,
+        }
+    }
+}
+
+#[cfg(all(feature = "toml-fmt", not(feature = "json-fmt")))]
+impl From<serde_fmt::de::Error> for Error {
+    fn from(err: serde_fmt::de::Error) -> Self {
+        // TOML locations start at zero
+        let location = err.line_col();
+        Error::Parse {
+            message: err.to_string(),
+            line: location.map(|(line, _)| line + 1),
+            column: location.map(|(_, column)| column + 1),
+        }
+    }
+}
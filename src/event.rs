@@ -34,28 +34,47 @@ use crate::components::ComponentID;
 pub enum Event {
     /// Mouse button action anywhere on the screen.
     ///
-    /// This event notifies all components that the user has clicked anywhere on the screen.
-    /// It is required that the component knows about its position to act upon this event.
-    /// To let a component know about its current position, the [`PositionChange`] event
-    /// can be used.
+    /// This event notifies the component below the pointer that the user has clicked on it.
+    /// It is required that the bar knows about the position of the component to route this
+    /// event. To let the bar know about the current position of a component, the
+    /// [`PositionChange`] event can be used.
     ///
     /// [`PositionChange`]: enum.Event.html#variant.PositionChange
     Click(MouseButton, MouseButtonState, Point),
 
     /// Update mouse position.
     ///
-    /// This event notifies all components about the current position of the mouse on the screen.
-    /// It is required that the component knows about its position to act upon this event.
-    /// To let a component know about its current position, the [`PositionChange`] event
-    /// can be used.
+    /// This event notifies the component below the pointer about the current position of the
+    /// mouse on the screen. It is required that the bar knows about the position of the component
+    /// to route this event. To let the bar know about the current position of a component, the
+    /// [`PositionChange`] event can be used.
     ///
     /// [`PositionChange`]: enum.Event.html#variant.PositionChange
     MouseMotion(Point),
 
+    /// Mouse pointer entered a component.
+    ///
+    /// This event is never sent by the frontend, instead the [`Bar`] synthesizes it from the
+    /// [`MouseMotion`] events and only sends it to the component which is now below the pointer.
+    ///
+    /// [`Bar`]: ../bar/struct.Bar.html
+    /// [`MouseMotion`]: enum.Event.html#variant.MouseMotion
+    MouseEnter(Point),
+
+    /// Mouse pointer left a component.
+    ///
+    /// This event is never sent by the frontend, instead the [`Bar`] synthesizes it from the
+    /// [`MouseMotion`] events and only sends it to the component which has been below the pointer
+    /// before.
+    ///
+    /// [`Bar`]: ../bar/struct.Bar.html
+    /// [`MouseMotion`]: enum.Event.html#variant.MouseMotion
+    MouseLeave(Point),
+
     /// Update the position of a component.
     ///
-    /// This event is used to make a component aware of its position on the screen. This is
-    /// required to react upon other events which are position dependent.
+    /// This event is used to make the bar and the component aware of the component's position on
+    /// the screen. This is required to route other events which are position dependent.
     PositionChange(ComponentPosition),
 }
 
@@ -99,6 +118,32 @@ pub struct ComponentPosition {
     pub max_y: usize,
 }
 
+impl ComponentPosition {
+    /// Check if a point on the screen is inside the component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bar_config::components::ComponentID;
+    /// use bar_config::event::{ComponentPosition, Point};
+    ///
+    /// let position = ComponentPosition {
+    ///     comp_id: ComponentID::default(),
+    ///     min_x: 0,
+    ///     max_x: 100,
+    ///     min_y: 0,
+    ///     max_y: 30,
+    /// };
+    ///
+    /// assert!(position.contains(Point { x: 100, y: 10 }));
+    /// assert!(!position.contains(Point { x: 101, y: 10 }));
+    /// ```
+    pub fn contains(&self, point: Point) -> bool {
+        let (x, y) = (point.x as usize, point.y as usize);
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+}
+
 /// Point on the screen.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Ord, PartialOrd)]
 pub struct Point {
@@ -66,12 +66,14 @@
 extern crate serde_derive;
 
 mod config;
+mod error;
 
 pub mod bar;
 pub mod components;
 pub mod event;
 
 pub use crate::config::{Background, Color};
+pub use crate::error::Error;
 pub use image;
 
 use dirs;
@@ -1,11 +1,26 @@
 #![feature(tool_lints)]
 #![allow(clippy::blacklisted_name)]
 
-use std::io::Cursor;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use bar_config;
-use bar_config::bar::Bar;
+use bar_config::bar::{Bar, Update};
+use bar_config::components::{
+    Component, ComponentID, ComponentRegistry, ComponentSettings, ComponentState, ComponentTrait,
+};
+use bar_config::event::{ComponentPosition, Event, MouseButton, MouseButtonState, Point};
 use image::{self, GenericImage};
+use serde::Deserialize;
 use time;
 
 #[test]
@@ -58,6 +73,1420 @@ fn clock_component() {
     assert_eq!(bar.left()[0].text(), format!("{}", time));
 }
 
+#[test]
+fn clock_timezone() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"clock\", format: \"%Z\", timezone: \"Asia/Tokyo\" }",
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "JST");
+}
+
+#[test]
+fn clock_invalid_timezone() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"clock\", timezone: \"Mars/Olympus_Mons\" }",
+    ));
+
+    assert!(Bar::load(input).is_err());
+}
+
+#[test]
+fn clock_alt_format() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"clock\", format: \"primary\", alt_format: \"alternate\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    assert_eq!(bar.left()[0].text(), "primary");
+
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+    let _ = bar.try_recv();
+
+    // Clicks outside of the component are ignored
+    let click = |x| {
+        Event::Click(
+            MouseButton::Left,
+            MouseButtonState::Released,
+            Point { x, y: 10 },
+        )
+    };
+    bar.notify(click(200));
+    bar.notify(click(50));
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].text(), "alternate");
+
+    bar.notify(click(50));
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].text(), "primary");
+}
+
+#[test]
+fn clock_calendar() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"clock\", format: \"%e\", timezone: \"UTC\" }",
+    ));
+
+    let bar = Bar::load(input).unwrap();
+    let day = bar.left()[0].text();
+
+    let tooltip = bar.left()[0].tooltip().unwrap();
+    let lines = tooltip.lines().collect::<Vec<_>>();
+    assert_eq!(lines[1], "Mo Tu We Th Fr Sa Su");
+    assert!(lines[2..].iter().all(|week| week.len() <= 20));
+    assert!(lines[2..]
+        .iter()
+        .any(|week| week.split_whitespace().any(|d| d == day.trim())));
+}
+
+#[test]
+fn battery_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"battery\", root: \"{}\", format: \"{{capacity}}% {{status}} {{time}} {{power}}W\" }}",
+        fixture("power_supply"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "50% discharging 2:30 10.0W");
+    assert_eq!(bar.left()[0].error(), None);
+}
+
+#[test]
+fn battery_charge() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"battery\", root: \"{}\", device: \"BAT1\", format: \"{{capacity}}% {{status}} {{time}} {{power}}W\" }}",
+        fixture("power_supply"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "75% charging 0:30 24.0W");
+}
+
+#[test]
+fn battery_missing() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"battery\", root: \"{}\", device: \"BAT2\" }}",
+        fixture("power_supply"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "");
+    assert!(bar.left()[0].error().is_some());
+}
+
+#[test]
+fn cpu_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"cpu\", proc: \"{}\", format: \"{{usage}} {{max}} {{core0}} {{cores}}\" }}",
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "58 75 75 75 25");
+    assert_eq!(
+        bar.left()[0].tooltip(),
+        Some(String::from("core0: 75%\ncore1: 25%"))
+    );
+}
+
+#[test]
+fn cpu_usage_delta() {
+    let dir = std::env::temp_dir().join(format!("bar-config-cpu-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stat"), "cpu 100 0 0 100 0 0 0 0 0 0\n").unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"cpu\", proc: \"{}\", interval: 10 }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    assert_eq!(bar.left()[0].text(), "50%");
+
+    // Only the time since the last sample is used
+    fs::write(dir.join("stat"), "cpu 110 0 0 190 0 0 0 0 0 0\n").unwrap();
+    assert_eq!(bar.recv(), Update::Component(bar.left()[0].id()));
+    assert_eq!(bar.left()[0].text(), "10%");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn memory_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"memory\", proc: \"{}\", format: \"{{mem_used}}/{{mem_total}} {{mem_percent}}% {{swap_used}} {{swap_percent}}%\" }}",
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "4.0GiB/8.0GiB 50% 512.0MiB 25%");
+}
+
+#[test]
+fn memory_invalid_base() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"memory\", base: 42 }",
+    ));
+
+    assert!(Bar::load(input).is_err());
+}
+
+#[test]
+fn memory_decimal_units() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"memory\", proc: \"{}\", format: \"{{mem_total}}\", base: 1000 }}",
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "8.6GB");
+}
+
+#[test]
+fn network_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"network\", proc: \"{}\", sys: \"{}\", format: \"{{interface}} {{state}} {{rx_total}} {{tx_total}} {{rx}}\" }}\n\
+         - {{ name: \"network\", proc: \"{}\", sys: \"{}\", interface: \"wlan0\", format: \"{{interface}} {{state}}\" }}",
+        fixture("proc"),
+        fixture("sys"),
+        fixture("proc"),
+        fixture("sys"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "eth0 up 3.0MiB 1.0MiB 0B");
+    assert_eq!(bar.left()[1].text(), "wlan0 down");
+}
+
+#[test]
+fn network_address() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"network\", proc: \"{}\", sys: \"{}\", interface: \"lo\", format: \"{{ipv4}}\" }}",
+        fixture("proc"),
+        fixture("sys"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "127.0.0.1");
+}
+
+#[test]
+fn network_missing_interface() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"network\", proc: \"{}\", sys: \"{}\", interface: \"eth1\" }}",
+        fixture("proc"),
+        fixture("sys"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert!(bar.left()[0].error().is_some());
+}
+
+#[test]
+fn disk_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"disk\", proc: \"{}\", mounts: [\"/\", \"/tmp\"], format: \"{{mount}}:{{fs}}\" }}",
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "/:ext4 /tmp:tmpfs");
+    assert_eq!(bar.left()[0].error(), None);
+}
+
+#[test]
+fn disk_discovery() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"disk\", proc: \"{0}\", discover: true, format: \"{{mount}}\" }}\n\
+         - {{ name: \"disk\", proc: \"{0}\", discover: true, include: [\"ext4\", \"tmpfs\"], exclude: [\"/nonexistent*\"], format: \"{{mount}}\", separator: \",\" }}",
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    // Mount points which cannot be read are reported as error
+    assert_eq!(bar.left()[0].text(), "/");
+    assert!(bar.left()[0]
+        .error()
+        .unwrap()
+        .contains("/nonexistent mount"));
+
+    assert_eq!(bar.left()[1].text(), "/,/tmp");
+    assert_eq!(bar.left()[1].error(), None);
+}
+
+#[test]
+fn temperature_hwmon() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"temperature\", sys: \"{0}\", hwmon: \"coretemp\", label: \"Core 0\" }}\n\
+         - {{ name: \"temperature\", sys: \"{0}\", hwmon: \"coretemp\", unit: \"fahrenheit\" }}",
+        fixture("sys"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "52°C");
+    assert_eq!(bar.left()[1].text(), "131°F");
+}
+
+#[test]
+fn temperature_thermal_zone() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"temperature\", sys: \"{0}\", warning: 50, critical: 80, format_warning: \"{{temperature}} warm\" }}\n\
+         - {{ name: \"temperature\", sys: \"{0}\", thermal_zone: \"x86_pkg_temp\", warning: 50, format_warning: \"{{temperature}} warm\" }}\n\
+         - {{ name: \"temperature\", sys: \"{0}\", thermal_zone: \"x86_pkg_temp\", warning: 50, critical: 80, format_critical: \"{{temperature}} {{state}}\" }}",
+        fixture("sys"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "40°C");
+    assert_eq!(bar.left()[1].text(), "85 warm");
+    assert_eq!(bar.left()[2].text(), "85 critical");
+}
+
+#[test]
+fn temperature_missing_sensor() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"temperature\", sys: \"{}\", hwmon: \"coretemp\", label: \"Core 7\" }}",
+        fixture("sys"),
+    ));
+
+    // Missing sensors are reported by the component instead of failing the bar
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "");
+    assert_eq!(
+        bar.left()[0].error(),
+        Some(String::from("no hwmon sensor Core 7 in coretemp"))
+    );
+}
+
+#[test]
+fn backlight_component() {
+    let dir = std::env::temp_dir().join(format!("bar-config-backlight-{}", std::process::id()));
+    let device = dir.join("class/backlight/intel_backlight");
+    fs::create_dir_all(&device).unwrap();
+    fs::write(device.join("brightness"), "500\n").unwrap();
+    fs::write(device.join("max_brightness"), "1000\n").unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"backlight\", sys: \"{}\", step: 10, min: 20 }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    assert_eq!(bar.left()[0].text(), "50%");
+
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+    let _ = bar.try_recv();
+
+    // Scrolling writes the new brightness
+    let scroll = |button| Event::Click(button, MouseButtonState::Pressed, Point { x: 50, y: 10 });
+    bar.notify(scroll(MouseButton::WheelUp));
+    while bar.left()[0].text() != "60%" {
+        let _ = bar.recv();
+    }
+    assert_eq!(
+        fs::read_to_string(device.join("brightness")).unwrap(),
+        "600"
+    );
+
+    // External changes are picked up without polling
+    fs::write(device.join("brightness"), "300\n").unwrap();
+    while bar.left()[0].text() != "30%" {
+        let _ = bar.recv();
+    }
+
+    // Brightness never goes below the minimum
+    bar.notify(scroll(MouseButton::WheelDown));
+    bar.notify(scroll(MouseButton::WheelDown));
+    while bar.left()[0].text() != "20%" {
+        let _ = bar.recv();
+    }
+    assert_eq!(
+        fs::read_to_string(device.join("brightness")).unwrap(),
+        "200"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn backlight_missing() {
+    let dir = std::env::temp_dir().join(format!("bar-config-no-backlight-{}", std::process::id()));
+    fs::create_dir_all(dir.join("class/backlight")).unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"backlight\", sys: \"{}\" }}",
+        dir.display(),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "");
+    assert_eq!(
+        bar.left()[0].error(),
+        Some(String::from("no backlight found"))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn component_states() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - name: \"battery\"\n  \
+           root: \"{}\"\n  \
+           foreground: \"#ffffff\"\n  \
+           fonts: [{{ name: \"Sans\", size: 10 }}]\n  \
+           states:\n    \
+             good: {{ above: 60, foreground: \"#00ff00\" }}\n    \
+             warning: {{ below: 60, foreground: \"#ffff00\", fonts: [{{ name: \"Bold\", size: 10 }}] }}\n    \
+             critical: {{ below: 20, foreground: \"#ff0000\" }}",
+        fixture("power_supply"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].value(), Some(50.));
+    assert_eq!(bar.left()[0].state(), Some(ComponentState::Warning));
+    let settings = bar.left()[0].settings();
+    assert_eq!(settings.foreground.unwrap().to_string(), "#ffff00ff");
+    assert_eq!(settings.fonts[0].name, "Bold");
+}
+
+#[test]
+fn component_state_transition() {
+    let dir = std::env::temp_dir().join(format!("bar-config-states-{}", std::process::id()));
+    let device = dir.join("class/backlight/intel_backlight");
+    fs::create_dir_all(&device).unwrap();
+    fs::write(device.join("brightness"), "500\n").unwrap();
+    fs::write(device.join("max_brightness"), "1000\n").unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"backlight\", sys: \"{}\", format: \"light\", foreground: \"#ffffff\", \
+              states: {{ critical: {{ below: 20, foreground: \"#ff0000\" }} }} }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    assert_eq!(bar.left()[0].state(), None);
+    let _ = bar.try_recv();
+
+    // Changing the state redraws the component, even though its text stays the same
+    fs::write(device.join("brightness"), "100\n").unwrap();
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].text(), "light");
+    assert_eq!(bar.left()[0].state(), Some(ComponentState::Critical));
+    let foreground = bar.left()[0].settings().foreground.unwrap();
+    assert_eq!(foreground.to_string(), "#ff0000ff");
+
+    // Leaving all states restores the original settings
+    fs::write(device.join("brightness"), "900\n").unwrap();
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].state(), None);
+    let foreground = bar.left()[0].settings().foreground.unwrap();
+    assert_eq!(foreground.to_string(), "#ffffffff");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn component_actions() {
+    let dir = std::env::temp_dir().join(format!("bar-config-actions-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ text: \"hello\", on_click_left: \"touch {0}/left\", on_click_right: \"exit 3\", \
+              on_scroll_up: \"touch {0}/up\", refresh_on_action: true }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    assert_eq!(bar.left()[0].text(), "hello");
+
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+
+    // Clicks outside of the component are ignored
+    let click = |button, state, x| Event::Click(button, state, Point { x, y: 10 });
+    bar.notify(click(MouseButton::Left, MouseButtonState::Released, 150));
+
+    // Buttons act once released, scrolling acts on every press
+    bar.notify(click(MouseButton::Left, MouseButtonState::Pressed, 50));
+    bar.notify(click(MouseButton::WheelUp, MouseButtonState::Pressed, 50));
+    wait_for(&dir.join("up"));
+    assert!(!dir.join("left").exists());
+    bar.notify(click(MouseButton::Left, MouseButtonState::Released, 50));
+    wait_for(&dir.join("left"));
+
+    // Failing commands are reported until the next successful action
+    assert_eq!(bar.left()[0].error(), None);
+    bar.notify(click(MouseButton::Right, MouseButtonState::Released, 50));
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert!(bar.left()[0].error().is_some());
+    bar.notify(click(MouseButton::Left, MouseButtonState::Released, 50));
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].error(), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn component_action_refresh() {
+    let dir = std::env::temp_dir().join(format!("bar-config-refresh-{}", std::process::id()));
+    let battery = dir.join("BAT0");
+    fs::create_dir_all(&battery).unwrap();
+    fs::write(battery.join("type"), "Battery\n").unwrap();
+    fs::write(battery.join("status"), "Full\n").unwrap();
+    fs::write(battery.join("capacity"), "50\n").unwrap();
+    fs::write(battery.join("energy_now"), "500\n").unwrap();
+    fs::write(battery.join("energy_full"), "1000\n").unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"battery\", root: \"{0}\", format: \"{{capacity}}%\", interval: 3600000, \
+              on_scroll_down: \"echo 40 > {0}/BAT0/capacity\", refresh_on_action: true }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    assert_eq!(bar.left()[0].text(), "50%");
+    let _ = bar.try_recv();
+
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+
+    // The component is updated right away instead of waiting for the next interval
+    let scroll = Event::Click(
+        MouseButton::WheelDown,
+        MouseButtonState::Pressed,
+        Point { x: 50, y: 10 },
+    );
+    bar.notify(scroll);
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].text(), "40%");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn component_action_refresh_command() {
+    let dir = std::env::temp_dir().join(format!("bar-config-rerun-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("file"), "old\n").unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"command\", exec: \"cat {0}/file\", on_click_left: \"echo new > {0}/file\", \
+              refresh_on_action: true }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].text(), "old");
+
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+
+    // The command is run again once the action has exited
+    let click = Event::Click(
+        MouseButton::Left,
+        MouseButtonState::Released,
+        Point { x: 50, y: 10 },
+    );
+    bar.notify(click);
+    assert_eq!(bar.recv(), Update::Component(comp_id));
+    assert_eq!(bar.left()[0].text(), "new");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn component_action_background() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"hello\", on_click_left: \"sleep 2\", refresh_on_action: true }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+
+    // Commands are awaited on the event loop instead of the caller's thread
+    let start = Instant::now();
+    let click = Event::Click(
+        MouseButton::Left,
+        MouseButtonState::Released,
+        Point { x: 50, y: 10 },
+    );
+    bar.notify(click);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn system_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"system\", proc: \"{}\", format: \"{{load1}} {{load5}} {{load15}} {{running}}/{{tasks}} {{uptime}}\" }}",
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "0.52 0.58 0.59 2/1234 4d 1h");
+}
+
+#[test]
+fn template_format_spec() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"system\", proc: \"{}\", format: \"{{load1:>6.1}}|{{running:03}}|{{tasks:*<6}}|{{uptime:.0h}}\" }}\n\
+         - {{ name: \"memory\", proc: \"{}\", format: \"{{mem_total:.0MiB}} {{swap_used:^9}} {{{{literal}}}}\" }}",
+        fixture("proc"),
+        fixture("proc"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "   0.5|002|1234**|97h");
+    assert_eq!(bar.left()[1].text(), "8192MiB 512.0MiB  {literal}");
+}
+
+#[test]
+fn template_conditional() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"battery\", root: \"{}\", format: \"{{capacity}}%{{?time: ({{time}})}}{{!time: full}}\" }}",
+        fixture("power_supply"),
+    ));
+
+    let bar = Bar::load(input).unwrap();
+
+    assert_eq!(bar.left()[0].text(), "50% (2:30)");
+}
+
+#[test]
+fn template_unknown_placeholder() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"memory\", format: \"{mem_used} {foo}\" }",
+    ));
+
+    assert!(Bar::load(input).is_err());
+}
+
+#[test]
+fn template_unclosed_placeholder() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"memory\", format: \"{mem_used\" }",
+    ));
+
+    assert!(Bar::load(input).is_err());
+}
+
+#[test]
+fn wifi_component() {
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"wifi\", proc: \"{0}\", format: \"{{icon}} {{ssid}} {{quality}}% {{signal}}dBm\", icons: [\"low\", \"mid\", \"high\"], ssid_command: \"printf '\\\\tSSID: %s net\\\\n' {{interface}}\" }}\n\
+         - {{ name: \"wifi\", proc: \"{0}\", interface: \"wlan1\", ssid_command: \"true\" }}\n\
+         - {{ name: \"wifi\", proc: \"{0}\", interface: \"wlan2\", format_down: \"offline\" }}",
+        fixture("proc"),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+
+    // Every component reads its state once the bar has been started
+    for _ in 0..3 {
+        let _ = bar.recv();
+    }
+
+    assert_eq!(bar.left()[0].text(), "high wlan0 net 80% -54dBm");
+    assert_eq!(bar.left()[1].text(), "wlan1 down");
+    assert_eq!(bar.left()[2].text(), "offline");
+}
+
+#[test]
+fn workspaces_component() {
+    let dir = std::env::temp_dir().join(format!("bar-config-ipc-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("ipc.sock");
+    let ipc = FakeIpc::start(&socket);
+    ipc.set_reply(
+        1,
+        "[\
+         { \"num\": 1, \"name\": \"1\", \"visible\": false, \"focused\": false, \"urgent\": false, \"output\": \"DVI-1\" },\
+         { \"num\": 2, \"name\": \"2\", \"visible\": true, \"focused\": true, \"urgent\": false, \"output\": \"DVI-1\" },\
+         { \"num\": 3, \"name\": \"3\", \"visible\": true, \"focused\": false, \"urgent\": false, \"output\": \"HDMI-2\" },\
+         { \"num\": 4, \"name\": \"4:mail\", \"visible\": false, \"focused\": false, \"urgent\": true, \"output\": \"DVI-1\" }\
+         ]",
+    );
+
+    let config = |monitor: &str| {
+        Cursor::new(format!(
+            "\
+             height: 30\n\
+             monitors:\n\
+             - {{ name: \"{}\", fallback_names: [\"DVI-1\"] }}\n\
+             left:\n\
+             - {{ name: \"workspaces\", socket: \"{}\", format_urgent: \"!{{name}}\" }}",
+            monitor,
+            socket.display(),
+        ))
+    };
+
+    // Only workspaces on the bar's monitors are shown
+    let mut bar = Bar::load(config("DP-1")).unwrap();
+    let comp_id = bar.left()[0].id();
+    let _ = bar.try_recv();
+    while bar.left()[0].text() != "1 [2] !4:mail" {
+        let _ = bar.recv();
+    }
+
+    // Clicking a label switches to its workspace
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 129,
+        min_y: 0,
+        max_y: 30,
+    }));
+    let click = |x| {
+        Event::Click(
+            MouseButton::Left,
+            MouseButtonState::Released,
+            Point { x, y: 10 },
+        )
+    };
+    bar.notify(click(5));
+    bar.notify(click(115));
+    assert_eq!(ipc.command(), "workspace \"1\"");
+    assert_eq!(ipc.command(), "workspace \"4:mail\"");
+
+    // Failed commands are reported by the component
+    ipc.set_reply(
+        0,
+        "[{ \"success\": false, \"error\": \"no such workspace\" }]",
+    );
+    bar.notify(click(5));
+    assert_eq!(ipc.command(), "workspace \"1\"");
+    while bar.left()[0].error().is_none() {
+        let _ = bar.recv();
+    }
+    assert_eq!(
+        bar.left()[0].error(),
+        Some(String::from("no such workspace"))
+    );
+
+    // Workspace events update the component
+    ipc.set_reply(
+        1,
+        "[\
+         { \"num\": 1, \"name\": \"1\", \"visible\": true, \"focused\": true, \"urgent\": false, \"output\": \"DVI-1\" },\
+         { \"num\": 3, \"name\": \"3\", \"visible\": true, \"focused\": false, \"urgent\": false, \"output\": \"HDMI-2\" }\
+         ]",
+    );
+    ipc.event(0x8000_0000);
+    while bar.left()[0].text() != "[1]" {
+        let _ = bar.recv();
+    }
+
+    // Changing the monitors keeps the component running
+    bar.reload(config("HDMI-2")).unwrap();
+    assert_eq!(bar.try_recv(), Some(Update::Component(comp_id)));
+    assert_eq!(bar.left()[0].text(), "3");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn window_title_component() {
+    let dir = std::env::temp_dir().join(format!("bar-config-title-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("ipc.sock");
+    let ipc = FakeIpc::start(&socket);
+
+    // Tree with a terminal on the first output and a browser focused on the second one
+    let tree = |focused_output: u32, title: &str| {
+        format!(
+            "{{ \"id\": 1, \"type\": \"root\", \"name\": \"root\", \"focus\": [3, 2], \"nodes\": [\
+             {{ \"id\": 2, \"type\": \"output\", \"name\": \"__i3\", \"focus\": [], \"nodes\": [] }},\
+             {{ \"id\": 3, \"type\": \"output\", \"name\": \"DVI-1\", \"focus\": [4], \"nodes\": [\
+             {{ \"id\": 4, \"type\": \"workspace\", \"name\": \"1\", \"focus\": [5], \"nodes\": [\
+             {{ \"id\": 5, \"type\": \"con\", \"name\": \"{}\", \"focused\": {}, \"window_properties\": {{ \"class\": \"Alacritty\" }} }}\
+             ] }} ] }},\
+             {{ \"id\": 6, \"type\": \"output\", \"name\": \"HDMI-2\", \"focus\": [7], \"nodes\": [\
+             {{ \"id\": 7, \"type\": \"workspace\", \"name\": \"2\", \"focus\": [9], \"nodes\": [\
+             {{ \"id\": 8, \"type\": \"con\", \"name\": \"Editor\" }}\
+             ], \"floating_nodes\": [\
+             {{ \"id\": 9, \"type\": \"floating_con\", \"name\": \"Browser\", \"app_id\": \"firefox\", \"focused\": {} }}\
+             ] }} ] }}\
+             ] }}",
+            title,
+            focused_output == 1,
+            focused_output == 2,
+        )
+    };
+    ipc.set_reply(4, &tree(2, "\\\"zsh\\\" \\u2192 \\ud83d\\ude80"));
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"window_title\", socket: \"{0}\", format: \"{{app}}: {{title}}\", max_length: 12 }}\n\
+         - {{ name: \"window_title\", socket: \"{0}\", format: \"{{title}} on {{output}}\" }}",
+        socket.display(),
+    ));
+
+    // The title of the window focused on the bar's own output is shown
+    let mut bar = Bar::load(input).unwrap();
+    let _ = bar.try_recv();
+    while bar.left()[0].text() != "Alacritty: \"zsh\" → 🚀" || bar.left()[1].text().is_empty() {
+        let _ = bar.recv();
+    }
+    assert_eq!(bar.left()[1].text(), "\"zsh\" → 🚀 on DVI-1");
+
+    // Window events update the title, truncating it to the maximum length
+    ipc.set_reply(4, &tree(1, "A very long terminal title"));
+    ipc.event(0x8000_0003);
+    while bar.left()[0].text() != "Alacritty: A very long…" {
+        let _ = bar.recv();
+    }
+    while bar.left()[1].text() != "A very long terminal title on DVI-1" {
+        let _ = bar.recv();
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mpd_component() {
+    let mpd = FakeMpd::start();
+    mpd.set_status(
+        "volume: 50\nstate: pause\nelapsed: 65.300\nduration: 200.000\n",
+        "file: music/foo.flac\nArtist: Foo\nTitle: Bar\n",
+    );
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"mpd\", host: \"127.0.0.1\", port: {}, format: \"{{state}} {{artist}} - {{title}} {{elapsed}}/{{duration}} {{volume}}%\", format_stopped: \"stopped\" }}",
+        mpd.port,
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    let _ = bar.try_recv();
+    while bar.left()[0].text() != "paused Foo - Bar 1:05/3:20 50%" {
+        let _ = bar.recv();
+    }
+
+    // Clicks are mapped to MPD commands
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+    let click = |button, state| Event::Click(button, state, Point { x: 50, y: 10 });
+    bar.notify(click(MouseButton::Left, MouseButtonState::Pressed));
+    bar.notify(click(MouseButton::Left, MouseButtonState::Released));
+    bar.notify(click(MouseButton::WheelUp, MouseButtonState::Pressed));
+    bar.notify(click(MouseButton::WheelDown, MouseButtonState::Pressed));
+    bar.notify(click(MouseButton::Right, MouseButtonState::Released));
+    assert_eq!(mpd.command(), "play");
+    assert_eq!(mpd.command(), "setvol 55");
+    assert_eq!(mpd.command(), "setvol 45");
+    assert_eq!(mpd.command(), "next");
+
+    // Changes of the player are received through idle
+    mpd.set_status("volume: 50\nstate: stop\n", "");
+    while bar.left()[0].text() != "stopped" {
+        let _ = bar.recv();
+    }
+}
+
+#[test]
+fn mpris_component() {
+    let dir = std::env::temp_dir().join(format!("bar-config-mpris-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let bus = FakeBus::start(&dir);
+
+    // The pinned player is preferred, even if another player is playing
+    let foo = FakePlayer::start(
+        &bus.address,
+        "org.mpris.MediaPlayer2.foo",
+        "Paused",
+        "Bar",
+        "Foo",
+    );
+    let baz = FakePlayer::start(
+        &bus.address,
+        "org.mpris.MediaPlayer2.baz",
+        "Playing",
+        "Qux",
+        "Baz",
+    );
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"mpris\", address: \"{0}\", player: \"foo\", format: \"{{status}} {{artist}} - {{title}} ({{player}})\", format_stopped: \"stopped\" }}\n\
+         - {{ name: \"mpris\", address: \"{0}\", format: \"{{artist}} - {{title}}\" }}",
+        bus.address,
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    let _ = bar.try_recv();
+    while bar.left()[0].text() != "paused Foo - Bar (foo)" {
+        let _ = bar.recv();
+    }
+    while bar.left()[1].text() != "Baz - Qux" {
+        let _ = bar.recv();
+    }
+
+    // Clicks are mapped to methods of the selected player
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+    let click = |button| Event::Click(button, MouseButtonState::Released, Point { x: 50, y: 10 });
+    bar.notify(click(MouseButton::Left));
+    bar.notify(click(MouseButton::Right));
+    bar.notify(click(MouseButton::Center));
+    assert_eq!(foo.call(), "PlayPause");
+    assert_eq!(foo.call(), "Next");
+    assert_eq!(foo.call(), "Previous");
+
+    // Errors of the player are reported until a click succeeds
+    foo.set_error(Some("no next track"));
+    bar.notify(click(MouseButton::Right));
+    assert_eq!(foo.call(), "Next");
+    while bar.left()[0].error().is_none() {
+        let _ = bar.recv();
+    }
+    assert_eq!(bar.left()[0].error(), Some(String::from("no next track")));
+    foo.set_error(None);
+    bar.notify(click(MouseButton::Right));
+    assert_eq!(foo.call(), "Next");
+    while bar.left()[0].error().is_some() {
+        let _ = bar.recv();
+    }
+
+    // Changes of the properties are received as signals
+    foo.set_properties("Playing", "Bar\tBaz", "Foo");
+    while bar.left()[0].text() != "playing Foo - Bar\tBaz (foo)" {
+        let _ = bar.recv();
+    }
+    foo.set_properties("Stopped", "", "");
+    while bar.left()[0].text() != "stopped" {
+        let _ = bar.recv();
+    }
+
+    // Closed players are removed once their name has been released
+    drop(baz);
+    while bar.left()[1].text() != "" {
+        let _ = bar.recv();
+    }
+
+    // Players are found when they are started after the bar
+    let _qux = FakePlayer::start(
+        &bus.address,
+        "org.mpris.MediaPlayer2.qux",
+        "Playing",
+        "Quux",
+        "Qux",
+    );
+    while bar.left()[1].text() != "Qux - Quux" {
+        let _ = bar.recv();
+    }
+
+    drop(bus);
+    fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn volume_component() {
+    let dir = std::env::temp_dir().join(format!("bar-config-volume-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("volume"), "50\n").unwrap();
+    fs::write(dir.join("mute"), "no\n").unwrap();
+    fs::write(dir.join("events"), "").unwrap();
+
+    // Fake pactl which stores the sink in files and emits an event for every change
+    //
+    // Commands are recorded before the sink changes, so they are complete once it is shown.
+    let pactl = dir.join("pactl");
+    fs::write(
+        &pactl,
+        "\
+         #!/bin/sh\n\
+         dir=$(dirname \"$0\")\n\
+         case \"$1\" in set-*) echo \"$*\" >> \"$dir/commands\" ;; esac\n\
+         case \"$1\" in\n\
+         subscribe) exec tail -n +1 -f \"$dir/events\" ;;\n\
+         get-sink-volume) v=$(cat \"$dir/volume\"); echo \"Volume: front-left: 0 / $v% / 0 dB,   front-right: 0 / $v% / 0 dB\" ;;\n\
+         get-sink-mute) echo \"Mute: $(cat \"$dir/mute\")\" ;;\n\
+         set-sink-volume) echo \"${3%?}\" > \"$dir/volume.new\"; mv \"$dir/volume.new\" \"$dir/volume\" ;;\n\
+         set-sink-mute) if [ \"$(cat \"$dir/mute\")\" = yes ]; then echo no; else echo yes; fi > \"$dir/mute.new\"; mv \"$dir/mute.new\" \"$dir/mute\" ;;\n\
+         esac\n\
+         case \"$1\" in set-*) echo \"Event 'change' on sink #0\" >> \"$dir/events\" ;; esac\n",
+    )
+    .unwrap();
+    fs::set_permissions(&pactl, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"volume\", pactl: \"{}\", format_muted: \"muted {{volume}}%\", max_volume: 55 }}",
+        pactl.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    let _ = bar.try_recv();
+    while bar.left()[0].text() != "50%" {
+        let _ = bar.recv();
+    }
+
+    // The wheel adjusts the volume up to the maximum
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+    let click = |button, state| Event::Click(button, state, Point { x: 50, y: 10 });
+    bar.notify(click(MouseButton::WheelUp, MouseButtonState::Pressed));
+    while bar.left()[0].text() != "55%" {
+        let _ = bar.recv();
+    }
+    bar.notify(click(MouseButton::WheelUp, MouseButtonState::Pressed));
+    bar.notify(click(MouseButton::WheelDown, MouseButtonState::Pressed));
+    while bar.left()[0].text() != "50%" {
+        let _ = bar.recv();
+    }
+
+    // Middle click toggles mute
+    bar.notify(click(MouseButton::Center, MouseButtonState::Pressed));
+    bar.notify(click(MouseButton::Center, MouseButtonState::Released));
+    while bar.left()[0].text() != "muted 50%" {
+        let _ = bar.recv();
+    }
+
+    assert_eq!(
+        fs::read_to_string(dir.join("commands")).unwrap(),
+        "\
+         set-sink-volume @DEFAULT_SINK@ 55%\n\
+         set-sink-volume @DEFAULT_SINK@ 55%\n\
+         set-sink-volume @DEFAULT_SINK@ 50%\n\
+         set-sink-mute @DEFAULT_SINK@ toggle\n"
+    );
+    assert_eq!(bar.left()[0].error(), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn command_component() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"command\", exec: \"echo first; echo second\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let _ = bar.recv();
+
+    assert_eq!(bar.left()[0].text(), String::from("first"));
+    assert_eq!(bar.left()[0].error(), None);
+}
+
+#[test]
+fn command_exit_status() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"command\", exec: \"echo output; exit 3\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let _ = bar.recv();
+
+    assert_eq!(bar.left()[0].text(), String::from("output"));
+    assert_eq!(
+        bar.left()[0].error(),
+        Some(String::from("command exited with status 3"))
+    );
+}
+
+#[test]
+fn command_timeout() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"command\", exec: \"sleep 5\", timeout: 10 }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let _ = bar.recv();
+
+    assert_eq!(
+        bar.left()[0].error(),
+        Some(String::from("command timed out after 10ms"))
+    );
+}
+
+#[test]
+fn command_interval() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"command\", exec: \"date +%N\", interval: 10 }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let _ = bar.recv();
+    let first = bar.left()[0].text();
+    let _ = bar.recv();
+
+    assert_ne!(bar.left()[0].text(), first);
+}
+
+#[test]
+fn command_restart_on_exit() {
+    let dir = std::env::temp_dir().join(format!("bar-config-restart-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let spawns = dir.join("spawns");
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"command\", exec: \"date +%s%N >> {}; echo done\", restart_on_exit: true }}",
+        spawns.display()
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let _ = bar.recv();
+
+    // Every spawn records its time in nanoseconds
+    let mut times = Vec::new();
+    for _ in 0..1000 {
+        let content = fs::read_to_string(&spawns).unwrap_or_default();
+        times = content
+            .lines()
+            .map(|line| line.parse::<u64>().unwrap())
+            .collect();
+        if times.len() >= 3 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(times.len() >= 3, "command spawned {} times", times.len());
+
+    // Restarts are delayed by a second, even when the command exits successfully
+    for spawn in times.windows(2) {
+        assert!(
+            spawn[1] - spawn[0] >= 1_000_000_000,
+            "restarted after {}ns",
+            spawn[1] - spawn[0]
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tail_component() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"tail\", exec: \"echo one; echo two; sleep 10\", placeholder: \"down\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    assert_eq!(bar.left()[0].text(), String::from("down"));
+
+    while bar.left()[0].text() != "two" {
+        let _ = bar.recv();
+    }
+}
+
+#[test]
+fn tail_exit() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"tail\", exec: \"echo one; exit 2\", placeholder: \"down\", backoff: 10000 }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+
+    while bar.left()[0].error().is_none() {
+        let _ = bar.recv();
+    }
+
+    assert_eq!(bar.left()[0].text(), String::from("down"));
+    assert_eq!(
+        bar.left()[0].error(),
+        Some(String::from("process exited with status 2"))
+    );
+}
+
 #[test]
 fn component_fallbacks() {
     let input = Cursor::new(String::from(
@@ -103,3 +1532,845 @@ fn load_image() {
         panic!("expected image but got color");
     }
 }
+
+struct CustomComponent {
+    id: ComponentID,
+    settings: ComponentSettings,
+    text: String,
+}
+
+impl ComponentTrait for CustomComponent {
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+
+    fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+}
+
+#[test]
+fn custom_component() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         defaults: { width: 100 }\n\
+         left:\n\
+         - { name: \"custom\", value: 42 }\n\
+         - { name: \"unknown\", text: \"fallback\" }",
+    ));
+
+    let mut registry = ComponentRegistry::default();
+    registry.register("custom", |settings, extra| {
+        Ok(Component::new(CustomComponent {
+            id: ComponentID::default(),
+            settings,
+            text: u64::deserialize(extra["value"].clone())?.to_string(),
+        }))
+    });
+
+    let bar = Bar::load_with_registry(input, registry).unwrap();
+
+    assert_eq!(bar.left()[0].text(), String::from("42"));
+    assert_eq!(bar.left()[0].settings().width, Some(100));
+    assert_eq!(bar.left()[1].text(), String::from("fallback"));
+}
+
+// Component recording all events it has been notified about
+struct RecordingComponent {
+    id: ComponentID,
+    settings: ComponentSettings,
+    events: Arc<Mutex<Vec<(ComponentID, Event)>>>,
+}
+
+impl ComponentTrait for RecordingComponent {
+    fn id(&self) -> ComponentID {
+        self.id
+    }
+
+    fn text(&self) -> String {
+        String::new()
+    }
+
+    fn settings(&self) -> &ComponentSettings {
+        &self.settings
+    }
+
+    fn notify(&mut self, event: Event) -> bool {
+        self.events.lock().unwrap().push((self.id, event));
+        false
+    }
+}
+
+#[test]
+fn tooltips() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         tooltip_delay: 10\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"hello\", tooltip: \"world\" }\n\
+         - { text: \"plain\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let (first, second) = (bar.left()[0].id(), bar.left()[1].id());
+    assert_eq!(bar.left()[0].tooltip(), Some(String::from("world")));
+    assert_eq!(bar.left()[1].tooltip(), None);
+
+    let position = |comp_id, min_x, max_x| ComponentPosition {
+        comp_id,
+        min_x,
+        max_x,
+        min_y: 0,
+        max_y: 30,
+    };
+    bar.notify(Event::PositionChange(position(first, 0, 49)));
+    bar.notify(Event::PositionChange(position(second, 50, 99)));
+
+    // Tooltips are shown once the pointer rests on a component
+    let point = |x| Point { x, y: 10 };
+    bar.notify(Event::MouseMotion(point(10)));
+    bar.notify(Event::MouseMotion(point(20)));
+    assert_eq!(bar.recv(), Update::ShowTooltip(first, point(20)));
+
+    // Leaving the component hides its tooltip right away
+    bar.notify(Event::MouseMotion(point(60)));
+    assert_eq!(bar.try_recv(), Some(Update::HideTooltip));
+
+    // Components without a tooltip never show one
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(bar.try_recv(), None);
+
+    // Clicking hides the tooltip until the pointer is moved again
+    bar.notify(Event::MouseMotion(point(30)));
+    assert_eq!(bar.recv(), Update::ShowTooltip(first, point(30)));
+    let click = Event::Click(MouseButton::Left, MouseButtonState::Released, point(30));
+    bar.notify(click);
+    assert_eq!(bar.try_recv(), Some(Update::HideTooltip));
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(bar.try_recv(), None);
+}
+
+#[test]
+fn tooltip_before_loop() {
+    let dir = std::env::temp_dir().join(format!("bar-config-hover-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let input = Cursor::new(format!(
+        "\
+         height: 30\n\
+         tooltip_delay: 10\n\
+         monitors:\n\
+         - {{ name: \"DVI-1\" }}\n\
+         left:\n\
+         - {{ name: \"command\", exec: \"touch {}/started\", tooltip: \"world\" }}",
+        dir.display(),
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let comp_id = bar.left()[0].id();
+    bar.notify(Event::PositionChange(ComponentPosition {
+        comp_id,
+        min_x: 0,
+        max_x: 100,
+        min_y: 0,
+        max_y: 30,
+    }));
+
+    // Moving the pointer does not start the streams of the components
+    let point = Point { x: 10, y: 10 };
+    bar.notify(Event::MouseMotion(point));
+    thread::sleep(Duration::from_millis(50));
+    assert!(!dir.join("started").exists());
+
+    // The tooltip is scheduled once the bar is started
+    assert_eq!(bar.recv(), Update::ShowTooltip(comp_id, point));
+    wait_for(&dir.join("started"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn event_routing() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { name: \"recording\" }\n\
+         - { name: \"recording\" }",
+    ));
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut registry = ComponentRegistry::default();
+    let registry_events = events.clone();
+    registry.register("recording", move |settings, _| {
+        Ok(Component::new(RecordingComponent {
+            id: ComponentID::default(),
+            settings,
+            events: registry_events.clone(),
+        }))
+    });
+
+    let mut bar = Bar::load_with_registry(input, registry).unwrap();
+    let (first, second) = (bar.left()[0].id(), bar.left()[1].id());
+
+    let position = |comp_id, min_x, max_x| ComponentPosition {
+        comp_id,
+        min_x,
+        max_x,
+        min_y: 0,
+        max_y: 30,
+    };
+    bar.notify(Event::PositionChange(position(first, 0, 49)));
+    bar.notify(Event::PositionChange(position(second, 50, 99)));
+    assert_eq!(bar.component_at(Point { x: 49, y: 10 }), Some(first));
+    assert_eq!(bar.component_at(Point { x: 50, y: 10 }), Some(second));
+    assert_eq!(bar.component_at(Point { x: 100, y: 10 }), None);
+
+    // Positions are only sent to their own component
+    let received = events.lock().unwrap().drain(..).collect::<Vec<_>>();
+    assert_eq!(
+        received,
+        vec![
+            (first, Event::PositionChange(position(first, 0, 49))),
+            (second, Event::PositionChange(position(second, 50, 99))),
+        ]
+    );
+
+    // Moving between components leaves the previous one before entering the next one
+    let point = |x| Point { x, y: 10 };
+    bar.notify(Event::MouseMotion(point(10)));
+    bar.notify(Event::MouseMotion(point(20)));
+    bar.notify(Event::MouseMotion(point(60)));
+    bar.notify(Event::MouseMotion(point(150)));
+    let received = events.lock().unwrap().drain(..).collect::<Vec<_>>();
+    assert_eq!(
+        received,
+        vec![
+            (first, Event::MouseEnter(point(10))),
+            (first, Event::MouseMotion(point(10))),
+            (first, Event::MouseMotion(point(20))),
+            (first, Event::MouseLeave(point(60))),
+            (second, Event::MouseEnter(point(60))),
+            (second, Event::MouseMotion(point(60))),
+            (second, Event::MouseLeave(point(150))),
+        ]
+    );
+
+    // Clicks are only sent to the component below the pointer
+    let click = |x| Event::Click(MouseButton::Left, MouseButtonState::Released, point(x));
+    bar.notify(click(60));
+    bar.notify(click(150));
+    let received = events.lock().unwrap().drain(..).collect::<Vec<_>>();
+    assert_eq!(received, vec![(second, click(60))]);
+}
+
+#[test]
+fn reload_config() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"unchanged\" }\n\
+         - { text: \"old\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let unchanged_id = bar.left()[0].id();
+    let changed_id = bar.left()[1].id();
+
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"unchanged\" }\n\
+         - { text: \"new\" }",
+    ));
+    bar.reload(input).unwrap();
+
+    assert_eq!(bar.left()[0].id(), unchanged_id);
+    assert_ne!(bar.left()[1].id(), changed_id);
+    assert_eq!(bar.left()[1].text(), String::from("new"));
+    assert_eq!(bar.try_recv(), Some(Update::Layout));
+    assert_eq!(bar.try_recv(), None);
+}
+
+#[test]
+fn reload_general() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"unchanged\" }",
+    ));
+
+    let mut bar = Bar::load(input).unwrap();
+    let id = bar.left()[0].id();
+
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         position: Top\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"unchanged\" }",
+    ));
+    bar.reload(input).unwrap();
+
+    assert_eq!(bar.left()[0].id(), id);
+    assert_eq!(bar.try_recv(), Some(Update::General));
+    assert_eq!(bar.try_recv(), None);
+}
+
+#[test]
+fn watch_config() {
+    let dir = std::env::temp_dir().join(format!("bar-config-watch-{}", std::process::id()));
+    let path = dir.join("bar.yml");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&path, "height: 30\nmonitors:\n  - { name: \"DVI-1\" }").unwrap();
+
+    let mut bar = Bar::load(fs::File::open(&path).unwrap()).unwrap();
+    bar.watch(&path).unwrap();
+
+    fs::write(&path, "height: 50\nmonitors:\n  - { name: \"DVI-1\" }").unwrap();
+
+    assert_eq!(bar.recv(), Update::General);
+    assert_eq!(bar.general().height, 50);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn watch_config_invalid() {
+    let dir = std::env::temp_dir().join(format!("bar-config-watch-invalid-{}", std::process::id()));
+    let path = dir.join("bar.yml");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&path, "height: 30\nmonitors:\n  - { name: \"DVI-1\" }").unwrap();
+
+    let mut bar = Bar::load(fs::File::open(&path).unwrap()).unwrap();
+    bar.watch(&path).unwrap();
+
+    // Invalid configurations are reported without changing the bar
+    fs::write(&path, "height: 50\nmonitors: []").unwrap();
+    assert_eq!(bar.recv(), Update::ReloadFailed);
+    assert_eq!(bar.general().height, 30);
+    let error = bar.reload_error().unwrap().to_string();
+    assert!(error.starts_with("invalid config"), "{}", error);
+
+    // The error is cleared once the file is valid again
+    fs::write(&path, "height: 50\nmonitors:\n  - { name: \"DVI-1\" }").unwrap();
+    assert_eq!(bar.recv(), Update::General);
+    assert!(bar.reload_error().is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Fake i3 IPC server which records all commands and broadcasts events to all subscribers
+struct FakeIpc {
+    replies: Arc<Mutex<HashMap<u32, String>>>,
+    subscribers: Arc<Mutex<Vec<UnixStream>>>,
+    commands: mpsc::Receiver<String>,
+}
+
+impl FakeIpc {
+    fn start(path: &Path) -> Self {
+        let listener = UnixListener::bind(path).unwrap();
+        let replies: Arc<Mutex<HashMap<u32, String>>> = Arc::new(Mutex::new(HashMap::new()));
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+        let (commands_tx, commands) = mpsc::channel();
+
+        let server_replies = replies.clone();
+        let server_subscribers = subscribers.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().filter_map(Result::ok) {
+                let replies = server_replies.clone();
+                let subscribers = server_subscribers.clone();
+                let commands_tx = commands_tx.clone();
+                thread::spawn(move || loop {
+                    let mut header = [0; 14];
+                    if stream.read_exact(&mut header).is_err() {
+                        break;
+                    }
+                    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+                    let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+                    let mut payload = vec![0; len as usize];
+                    stream.read_exact(&mut payload).unwrap();
+
+                    let reply = match kind {
+                        0 => {
+                            let _ = commands_tx.send(String::from_utf8(payload).unwrap());
+                            replies
+                                .lock()
+                                .unwrap()
+                                .get(&kind)
+                                .cloned()
+                                .unwrap_or_else(|| String::from("[{ \"success\": true }]"))
+                        }
+                        2 => {
+                            let subscriber = stream.try_clone().unwrap();
+                            subscribers.lock().unwrap().push(subscriber);
+                            String::from("{ \"success\": true }")
+                        }
+                        _ => replies.lock().unwrap()[&kind].clone(),
+                    };
+                    stream.write_all(&ipc_message(kind, &reply)).unwrap();
+                });
+            }
+        });
+
+        FakeIpc {
+            replies,
+            subscribers,
+            commands,
+        }
+    }
+
+    fn set_reply(&self, kind: u32, reply: &str) {
+        self.replies.lock().unwrap().insert(kind, reply.to_owned());
+    }
+
+    fn event(&self, kind: u32) {
+        let event = ipc_message(kind, "{ \"change\": \"focus\" }");
+        for subscriber in self.subscribers.lock().unwrap().iter_mut() {
+            subscriber.write_all(&event).unwrap();
+        }
+    }
+
+    fn command(&self) -> String {
+        self.commands.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+}
+
+// Fake MPD server which records all commands and notifies idle clients about changes
+struct FakeMpd {
+    port: u16,
+    status: Arc<Mutex<(String, String)>>,
+    idle: Arc<Mutex<Vec<TcpStream>>>,
+    commands: mpsc::Receiver<String>,
+}
+
+impl FakeMpd {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let status = Arc::new(Mutex::new((String::new(), String::new())));
+        let idle = Arc::new(Mutex::new(Vec::new()));
+        let (commands_tx, commands) = mpsc::channel();
+
+        let server_status = status.clone();
+        let server_idle = idle.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().filter_map(Result::ok) {
+                let status = server_status.clone();
+                let idle = server_idle.clone();
+                let commands_tx = commands_tx.clone();
+                thread::spawn(move || {
+                    stream.write_all(b"OK MPD 0.21.0\n").unwrap();
+
+                    // Changes since the last status return from idle immediately, like MPD
+                    let mut seen = None;
+                    let reader = BufReader::new(stream.try_clone().unwrap());
+                    for line in reader.lines().filter_map(Result::ok) {
+                        let reply = match line.as_str() {
+                            "status" => {
+                                let status = status.lock().unwrap().clone();
+                                seen = Some(status.clone());
+                                status.0 + "OK\n"
+                            }
+                            "currentsong" => status.lock().unwrap().1.clone() + "OK\n",
+                            "idle player mixer" => {
+                                let mut idle = idle.lock().unwrap();
+                                if seen.as_ref() == Some(&*status.lock().unwrap()) {
+                                    idle.push(stream.try_clone().unwrap());
+                                    continue;
+                                }
+                                String::from("changed: player\nOK\n")
+                            }
+                            _ => {
+                                let _ = commands_tx.send(line);
+                                String::from("OK\n")
+                            }
+                        };
+                        stream.write_all(reply.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+
+        FakeMpd {
+            port,
+            status,
+            idle,
+            commands,
+        }
+    }
+
+    fn set_status(&self, status: &str, song: &str) {
+        *self.status.lock().unwrap() = (status.to_owned(), song.to_owned());
+
+        for mut client in self.idle.lock().unwrap().drain(..) {
+            client.write_all(b"changed: player\nOK\n").unwrap();
+        }
+    }
+
+    fn command(&self) -> String {
+        self.commands.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+}
+
+// Private D-Bus session bus, which is stopped when dropped
+struct FakeBus {
+    daemon: Child,
+    address: String,
+}
+
+impl FakeBus {
+    fn start(dir: &Path) -> Self {
+        let config = dir.join("bus.conf");
+        fs::write(
+            &config,
+            format!(
+                "<busconfig>\
+                 <type>session</type>\
+                 <listen>unix:path={}</listen>\
+                 <auth>EXTERNAL</auth>\
+                 <policy context=\"default\">\
+                 <allow send_destination=\"*\" eavesdrop=\"true\"/>\
+                 <allow eavesdrop=\"true\"/>\
+                 <allow own=\"*\"/>\
+                 </policy>\
+                 </busconfig>",
+                dir.join("bus").display()
+            ),
+        )
+        .unwrap();
+
+        let mut daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(&["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        // The address is printed once the bus accepts connections
+        let mut address = String::new();
+        let mut stdout = BufReader::new(daemon.stdout.take().unwrap());
+        stdout.read_line(&mut address).unwrap();
+
+        FakeBus {
+            daemon,
+            address: address.trim().to_owned(),
+        }
+    }
+}
+
+impl Drop for FakeBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+// Fake MPRIS player on a bus, which records all calls of its methods
+struct FakePlayer {
+    writer: Arc<Mutex<UnixStream>>,
+    properties: Arc<Mutex<(String, String, String)>>,
+    error: Arc<Mutex<Option<String>>>,
+    calls: mpsc::Receiver<String>,
+}
+
+impl FakePlayer {
+    fn start(address: &str, name: &str, status: &str, title: &str, artist: &str) -> Self {
+        let path = address.trim_start_matches("unix:path=").split(',').next();
+        let mut stream = UnixStream::connect(path.unwrap()).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let uid = unsafe { libc::getuid() }.to_string();
+        let uid = uid
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let auth = format!("\0AUTH EXTERNAL {}\r\n", uid);
+        stream.write_all(auth.as_bytes()).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("OK"));
+        stream.write_all(b"BEGIN\r\n").unwrap();
+
+        let bus_fields = |member| {
+            vec![
+                (1, "o", "/org/freedesktop/DBus"),
+                (2, "s", "org.freedesktop.DBus"),
+                (3, "s", member),
+                (6, "s", "org.freedesktop.DBus"),
+            ]
+        };
+        let hello = dbus_message(1, 1, &bus_fields("Hello"), "", DbusWriter::default());
+        stream.write_all(&hello).unwrap();
+        let mut body = DbusWriter::default();
+        body.string(name);
+        body.u32(0);
+        let request_name = dbus_message(1, 2, &bus_fields("RequestName"), "su", body);
+        stream.write_all(&request_name).unwrap();
+
+        let properties = (status.to_owned(), title.to_owned(), artist.to_owned());
+        let properties = Arc::new(Mutex::new(properties));
+        let writer = Arc::new(Mutex::new(stream));
+        let error: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+        let (calls_tx, calls) = mpsc::channel();
+
+        let player_properties = properties.clone();
+        let player_writer = writer.clone();
+        let player_error = error.clone();
+        thread::spawn(move || {
+            let mut serial = 100;
+            while let Some((kind, call_serial, fields)) = read_dbus_message(&mut reader) {
+                let (sender, member) = match (kind, fields.get(&7), fields.get(&3)) {
+                    (1, Some(sender), Some(member)) => (sender.clone(), member.clone()),
+                    _ => continue,
+                };
+
+                let call_serial = call_serial.to_string();
+                let fields = [(5, "u", call_serial.as_str()), (6, "s", sender.as_str())];
+                let reply = if member == "GetAll" {
+                    let mut body = DbusWriter::default();
+                    body.properties(&player_properties.lock().unwrap());
+                    dbus_message(2, serial, &fields, "a{sv}", body)
+                } else {
+                    let _ = calls_tx.send(member);
+                    match &*player_error.lock().unwrap() {
+                        Some(error) => {
+                            let mut body = DbusWriter::default();
+                            body.string(error);
+                            let name = (4, "s", "org.mpris.MediaPlayer2.Error");
+                            let fields = [name, fields[0], fields[1]];
+                            dbus_message(3, serial, &fields, "s", body)
+                        }
+                        None => dbus_message(2, serial, &fields, "", DbusWriter::default()),
+                    }
+                };
+                serial += 1;
+
+                if player_writer.lock().unwrap().write_all(&reply).is_err() {
+                    break;
+                }
+            }
+        });
+
+        FakePlayer {
+            writer,
+            properties,
+            error,
+            calls,
+        }
+    }
+
+    fn set_error(&self, error: Option<&str>) {
+        *self.error.lock().unwrap() = error.map(str::to_owned);
+    }
+
+    fn set_properties(&self, status: &str, title: &str, artist: &str) {
+        let properties = (status.to_owned(), title.to_owned(), artist.to_owned());
+
+        let mut body = DbusWriter::default();
+        body.string("org.mpris.MediaPlayer2.Player");
+        body.properties(&properties);
+        body.array(4, |_| ());
+        *self.properties.lock().unwrap() = properties;
+
+        let fields = [
+            (1, "o", "/org/mpris/MediaPlayer2"),
+            (2, "s", "org.freedesktop.DBus.Properties"),
+            (3, "s", "PropertiesChanged"),
+        ];
+        let signal = dbus_message(4, 3, &fields, "sa{sv}as", body);
+        self.writer.lock().unwrap().write_all(&signal).unwrap();
+    }
+
+    fn call(&self) -> String {
+        self.calls.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+}
+
+impl Drop for FakePlayer {
+    fn drop(&mut self) {
+        let _ = self.writer.lock().unwrap().shutdown(Shutdown::Both);
+    }
+}
+
+// Encoder for values in the little endian D-Bus wire format
+#[derive(Default)]
+struct DbusWriter(Vec<u8>);
+
+impl DbusWriter {
+    fn align(&mut self, alignment: usize) {
+        while self.0.len() % alignment != 0 {
+            self.0.push(0);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+    }
+
+    fn signature(&mut self, value: &str) {
+        self.0.push(value.len() as u8);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+    }
+
+    // Write an array, its length is only known once all elements have been written
+    fn array(&mut self, alignment: usize, elements: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let offset = self.0.len() - 4;
+        self.align(alignment);
+        let start = self.0.len();
+        elements(self);
+        let len = (self.0.len() - start) as u32;
+        self.0[offset..offset + 4].copy_from_slice(&len.to_le_bytes());
+    }
+
+    // Write an entry of a `a{sv}` dictionary
+    fn entry(&mut self, key: &str, signature: &str, value: impl FnOnce(&mut Self)) {
+        self.align(8);
+        self.string(key);
+        self.signature(signature);
+        value(self);
+    }
+
+    fn properties(&mut self, (status, title, artist): &(String, String, String)) {
+        self.array(8, |w| {
+            w.entry("PlaybackStatus", "s", |w| w.string(status));
+            w.entry("Metadata", "a{sv}", |w| {
+                w.array(8, |w| {
+                    w.entry("xesam:title", "s", |w| w.string(title));
+                    w.entry("xesam:artist", "as", |w| w.array(4, |w| w.string(artist)));
+                })
+            });
+        });
+    }
+}
+
+// Encode a D-Bus message with the given header fields and body
+fn dbus_message(
+    kind: u8,
+    serial: u32,
+    fields: &[(u8, &str, &str)],
+    signature: &str,
+    body: DbusWriter,
+) -> Vec<u8> {
+    let mut message = DbusWriter(vec![b'l', kind, 0, 1]);
+    message.u32(body.0.len() as u32);
+    message.u32(serial);
+
+    let signature_field = (8, "g", signature);
+    let fields = fields
+        .iter()
+        .chain(Some(&signature_field).filter(|_| !signature.is_empty()));
+    message.array(8, |message| {
+        for (code, kind, value) in fields {
+            message.align(8);
+            message.0.push(*code);
+            message.signature(kind);
+            match *kind {
+                "u" => message.u32(value.parse().unwrap()),
+                "g" => message.signature(value),
+                _ => message.string(value),
+            }
+        }
+    });
+
+    message.align(8);
+    message.0.extend_from_slice(&body.0);
+    message.0
+}
+
+// Read a D-Bus message, returning its type, serial and string header fields
+fn read_dbus_message(reader: &mut impl Read) -> Option<(u8, u32, HashMap<u8, String>)> {
+    let mut data = vec![0; 16];
+    reader.read_exact(&mut data).ok()?;
+    let read_u32 = |data: &[u8], pos: usize| {
+        u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize
+    };
+
+    let fields_end = 16 + read_u32(&data, 12);
+    let len = (fields_end + 7) / 8 * 8 + read_u32(&data, 4);
+    data.resize(len, 0);
+    reader.read_exact(&mut data[16..]).ok()?;
+
+    // Every field is a code followed by a variant with a single character signature
+    let mut fields = HashMap::new();
+    let mut pos = 16;
+    while pos < fields_end {
+        pos = (pos + 7) / 8 * 8;
+        let (code, kind) = (data[pos], data[pos + 2]);
+        pos += 4;
+        let value_len = match kind {
+            b'g' => {
+                pos += 1;
+                usize::from(data[pos - 1])
+            }
+            _ => {
+                pos = (pos + 3) / 4 * 4 + 4;
+                if kind == b'u' {
+                    continue;
+                }
+                read_u32(&data, pos - 4)
+            }
+        };
+        let value = String::from_utf8_lossy(&data[pos..pos + value_len]).into_owned();
+        fields.insert(code, value);
+        pos += value_len + 1;
+    }
+
+    Some((data[1], read_u32(&data, 8) as u32, fields))
+}
+fn ipc_message(kind: u32, payload: &str) -> Vec<u8> {
+    let mut message = b"i3-ipc".to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
+
+// Wait until a file has been created by a command running in the background
+fn wait_for(path: &Path) {
+    for _ in 0..500 {
+        if path.exists() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("{} has not been created", path.display());
+}
+
+// Path of a fake system directory in the test fixtures
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
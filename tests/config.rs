@@ -3,7 +3,9 @@
 
 use std::io::Cursor;
 
-use bar_config::bar::Bar;
+use bar_config::bar::{Alignment, Bar};
+use bar_config::components::ComponentSettings;
+use bar_config::Error;
 
 #[allow(clippy::float_cmp)]
 #[test]
@@ -46,3 +48,84 @@ fn colors_as_f64() {
     assert_eq!(foreground.2, 1.0);
     assert_eq!(foreground.3, 0.6);
 }
+
+#[test]
+fn parse_error_location() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         position: Middle",
+    ));
+
+    match Bar::load(input) {
+        Err(Error::Parse { line, .. }) => assert_eq!(line, Some(4)),
+        _ => panic!("expected parse error"),
+    }
+}
+
+#[test]
+fn missing_monitors() {
+    let input = Cursor::new(String::from("height: 30\nmonitors: []"));
+
+    match Bar::load(input) {
+        Err(Error::Validation(_)) => (),
+        _ => panic!("expected validation error"),
+    }
+}
+
+#[test]
+fn missing_image() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         background: \"./tests/missing.png\"",
+    ));
+
+    match Bar::load(input) {
+        Err(Error::Image { path, .. }) => assert_eq!(path.to_str(), Some("./tests/missing.png")),
+        _ => panic!("expected image error"),
+    }
+}
+
+#[test]
+fn invalid_component_options() {
+    let input = Cursor::new(String::from(
+        "\
+         height: 30\n\
+         monitors:\n\
+         - { name: \"DVI-1\" }\n\
+         left:\n\
+         - { text: \"first\" }\n\
+         - { text: \"second\" }\n\
+         - { name: \"clock\", interval: \"ten\" }",
+    ));
+
+    match Bar::load(input) {
+        Err(err @ Error::Component { .. }) => {
+            assert!(err.to_string().starts_with("invalid options in left[2]"));
+            if let Error::Component {
+                alignment, index, ..
+            } = err
+            {
+                assert_eq!(alignment, Alignment::Left);
+                assert_eq!(index, 2);
+            }
+        }
+        _ => panic!("expected component error"),
+    }
+}
+
+#[test]
+fn deserialize_component_settings() {
+    let input = r##"{ "foreground": "#ff0000", "background": "#00ff00", "width": 20 }"##;
+
+    let settings: ComponentSettings = serde_json::from_str(input).unwrap();
+
+    assert_eq!(settings.foreground.unwrap().to_string(), "#ff0000ff");
+    assert_eq!(settings.width, Some(20));
+    assert!(settings.fonts.is_empty());
+}